@@ -130,6 +130,12 @@ impl From<Vec<FileDependency>> for Dependencies {
     }
 }
 
+impl From<Vec<Dependency>> for Dependencies {
+    fn from(deps: Vec<Dependency>) -> Self {
+        Self(deps)
+    }
+}
+
 #[derive(Deserialize,Serialize)]
 #[serde(rename_all="camelCase")]
 struct DepIntermediate {
@@ -153,10 +159,8 @@ impl<'de> Deserialize<'de> for Dependency {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de> {
-        DepIntermediate::deserialize(deserializer)
-        .map(|dep| 
-            Self::from_idx(dep.r#type,dep.addon_id)
-                .unwrap_or_else(|| panic!("Unknown DependencyType {}",dep.r#type))
-        )
+        let dep = DepIntermediate::deserialize(deserializer)?;
+        Self::from_idx(dep.r#type,dep.addon_id)
+            .ok_or_else(|| serde::de::Error::custom(format!("Unknown dependency type {} of addon {}",dep.r#type,dep.addon_id)) )
     }
 }
@@ -7,15 +7,16 @@ use std::str::FromStr;
 use std::io::BufRead;
 use std::time::Duration;
 
-use crate::api::{API, parse_retry_duration};
+use crate::api::{API, HttpClient, parse_retry_duration};
 use crate::conf::Conf;
 use crate::*;
-use crate::util::fs::{Finalize, is_existing, is_file_or_symlink, create_guarded_symlink, attached_to_path, create_guarded_symlink_lazy};
+use crate::util::fs::{Finalize, CacheLinkMode, part_file_path, is_existing, is_file_or_symlink, create_guarded_symlink, attached_to_path, create_guarded_cache_link};
 
 use anyhow::{anyhow, bail};
 use chrono::DateTime;
 use filetime::{FileTime, set_file_times};
 use sha1::{Sha1, Digest};
+use sha2::{Sha256, Sha512};
 use util::fs::remove_if;
 
 use super::AddonID;
@@ -25,44 +26,121 @@ impl AddonFile {
     pub fn download(&self, paths: &FilePaths, conf: &Conf, api: &mut API, cache_only: bool) -> Result<Finalize,anyhow::Error> {
         conf.ensure_cache_dir()?;
 
-        let file_length = try_from!(self.file_length,anyhow!("file too big"));
-
-        let mut validated = None;
+        let download_to = paths.cache_path.as_ref().unwrap_or(&paths.part_path);
         
-        match self.is_downloaded_valid(paths) {
-            Ok(v) => validated = v,
+        assert_eq!(paths.cache_path.is_some(), conf.symlink_cache_path.is_some());
+
+        // the file may already be fetched by prefetch
+        let validated = match self.is_downloaded_addon_valid(download_to) {
+            Ok(v) => v,
             Err(e) if e.downcast_ref::<std::io::Error>()
-                .map_or(false, |e| e.kind() == std::io::ErrorKind::NotFound ) => {},
-            Err(e) => warn!("{}",e),
+                .map_or(false, |e| e.kind() == std::io::ErrorKind::NotFound ) => None,
+            Err(e) => {warn!("{}",e);None},
+        };
+
+        let validated = match validated {
+            Some(v) => v,
+            None => self.fetch(download_to, conf, &api.http())?,
+        };
+
+        if cache_only {
+            return Ok(Finalize::noop());
+        }
+
+        // write addon publish time and current time to mtime and atime
+        let addon_time = conf.addon_mtime
+            .then(|| log_error!(parse_date(&self.file_date)) )
+            .flatten()
+            .map(|addon_time| (FileTime::now(),FileTime::from_unix_time(addon_time.timestamp(),0)) );
+
+        // set before the cache file is placed, so that copies of it get the time too
+        if let Some((now,addon_time)) = addon_time {
+            if let Some(cache_path) = &paths.cache_path {
+                log_error!(set_file_times(cache_path, now, addon_time),   |e| "Failed to set file time for cache_path: {}",e);
+            } else {
+                log_error!(set_file_times(&paths.part_path, now, addon_time),   |e| "Failed to set file time for path: {}",e);
+            }
+        }
+
+        let mut finalizer = if let Some(cache_path) = &paths.cache_path {
+            create_guarded_cache_link(cache_path.clone(), paths.path.clone(), conf.cache_link_mode)?
+        } else {
+            Finalize::for_part_path(paths.path.clone(), download_to.clone(), false)
+        };
+
+        if conf.url_txt {
+            finalizer = finalizer + self.write_url_txt(paths, conf, api, &validated)?;
+        }
+
+        if let Some((now,addon_time)) = addon_time {
+            if paths.url_txt_path.is_file() {
+                log_error!(set_file_times(&paths.url_txt_path, now, addon_time),|e| "Failed to set file time url_txt_path: {}",e);
+            }
+        }
+
+        Ok(finalizer)
+    }
+
+    /// validate and download into the cache or part path, so that validate_download only needs to create the finalizer
+    /// 
+    /// Safe to run for multiple addons in parallel
+    pub fn prefetch(&self, paths: &FilePaths, conf: &Conf, http: &HttpClient, cache_only: bool) -> Result<(),anyhow::Error> {
+        conf.ensure_cache_dir()?;
+
+        if self.validate(paths, cache_only)?.file_valid {
+            return Ok(());
         }
 
         let download_to = paths.cache_path.as_ref().unwrap_or(&paths.part_path);
-        
-        assert_eq!(paths.cache_path.is_some(), conf.symlink_cache_path.is_some());
+
+        if !matches!(self.is_downloaded_addon_valid(download_to), Ok(Some(_))) {
+            self.fetch(download_to, conf, http)?;
+        }
+
+        Ok(())
+    }
+
+    /// download to path, retries on soft-errors like hash mismatch, returns the SHA-1 of the file
+    /// 
+    /// The file is streamed into a .part file and hashed in the same pass, an existing .part is resumed if the server supports ranges
+    pub fn fetch(&self, download_to: &Path, conf: &Conf, http: &HttpClient) -> Result<String,anyhow::Error> {
+        let file_length = self.file_length;
+
+        // download_to may already be the .part path if there is no cache
+        let part_path = if download_to.extension().map_or(false, |e| e == "part" ) {
+            download_to.to_owned()
+        } else {
+            part_file_path(download_to)
+        };
 
         let mut soft_error: Option<anyhow::Error> = None;
-        // retries on soft-errors like hash mismatch
+
         for retry_i in 0..conf.soft_retries.max(1) {
             if let Some(soft_error) = &soft_error {
                 error!("Error: {soft_error}, retry download");
             }
 
-            if validated.is_none() {
-                let mut buf = Vec::with_capacity(file_length);
+            let download_url = self.download_url.as_ref()
+                .ok_or_else(|| anyhow::anyhow!("No download link") )?;
+
+            let mut hasher = StreamHasher::new(self);
+
+            let (mut reader,mut out): (Box<dyn Read>,File) = if let Some(local_path) = download_url.local_path_in(&conf.repo_dir) {
+                (Box::new(file_read(local_path)?),File::create(&part_path)?)
+            } else {
+                let existing = part_path.metadata().map_or(0, |m| m.len() );
+                let resume = existing > 0 && existing < file_length;
 
-                let download_url = self.download_url.as_ref()
-                    .ok_or_else(|| anyhow::anyhow!("No download link") )?;
-                
-                let resp = match api.http_get(&download_url.0) {
+                let resp = match if resume {http.get_range(&download_url.0, existing)} else {http.get(&download_url.0)} {
                     Err(e) => {
-                        if let ureq::Error::Status(429, response) = &e {
+                        if let Some(ureq::Error::Status(429, response)) = e.downcast_ref::<ureq::Error>() {
                             let wait_duration = parse_retry_duration(
                                 response.header("Retry-After"),
                                 4u64.pow(retry_i.min(3)),
                             );
                             error!("Too many requests, retry in {wait_duration} seconds");
-                            soft_error = Some(e.into());
-                            std::thread::sleep(Duration::from_secs( 4u64.pow(retry_i.min(3)) ));
+                            soft_error = Some(e);
+                            std::thread::sleep(Duration::from_secs(wait_duration));
                             continue;
                         } else {
                             Err(e)
@@ -71,62 +149,69 @@ impl AddonFile {
                     v => v,
                 }?;
 
-                resp.into_reader().read_to_end(&mut buf)?;
-
-                soft_assert!(buf.len() == file_length, anyhow!("file_length mismatch"), soft_error);
-
-                // hash the downloaded data
-                let sha = {
-                    let mut hasher = Sha1::new();
-                    hasher.update(&buf);
-                    hasher.finalize()
+                let out = if resume && resp.status() == 206 {
+                    dark_log!("Resume download at {} of {} bytes",existing,file_length);
+                    // hash the already downloaded part, then append to it
+                    let mut out = OpenOptions::new().read(true).append(true).open(&part_path)?;
+                    std::io::copy(&mut out, &mut hasher)?;
+                    out
+                } else {
+                    File::create(&part_path)?
                 };
-                let sha_str = hex::encode(&*sha);
 
-                if let Some(sha1_hash) = &self.sha1_hash {
-                    soft_assert!(sha_str == *sha1_hash, anyhow!("File Hash mismatch"), soft_error);
-                }
+                (Box::new(resp.into_reader()),out)
+            };
 
-                std::fs::write(download_to, &buf)?;
+            // stream into the part file while hashing
+            let mut buf = vec![0u8;64*1024];
+            let copied = loop {
+                let n = match reader.read(&mut buf) {
+                    Ok(0) => break Ok(()),
+                    Ok(n) => n,
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(e) => break Err(e),
+                };
+                hasher.update(&buf[..n]);
+                if hasher.len > file_length {break Ok(())}
+                if let Err(e) = out.write_all(&buf[..n]) {break Err(e)}
+            };
+            drop(out);
 
-                validated = Some(sha_str);
+            // an interrupted download is kept to be resumed on retry
+            if let Err(e) = copied {
+                soft_error = Some(e.into());
+                continue;
             }
-            
-            if cache_only {
-                return Ok(Finalize::noop());
+
+            if hasher.len != file_length {
+                remove_if(&part_path)?;
+                soft_error = Some(anyhow!("file_length mismatch"));
+                continue;
             }
 
-            let mut finalizer = if let Some(cache_path) = &paths.cache_path {
-                create_guarded_symlink_lazy(cache_path.clone(), paths.path.clone())?
-            } else {
-                Finalize::for_part_path(paths.path.clone(), download_to.clone(), false)
-            };
+            let hashes = hasher.finalize();
 
-            if conf.url_txt {
-                finalizer = finalizer + self.write_url_txt(paths, conf, api, validated.as_ref().unwrap())?;
+            if !self.matches_hashes(&hashes) {
+                remove_if(&part_path)?;
+                soft_error = Some(anyhow!("File Hash mismatch"));
+                continue;
             }
 
-            if conf.addon_mtime {
-                // write addon publish time and current time to mtime and atime
-                if let Some(addon_time) = log_error!(parse_date(&self.file_date)) {
-                    let addon_time = FileTime::from_unix_time(addon_time.timestamp(),0);
-                    let now = FileTime::now();
-                    if let Some(cache_path) = &paths.cache_path {
-                        log_error!(set_file_times(cache_path, now, addon_time),   |e| "Failed to set file time for cache_path: {}",e);
-                    } else {
-                        log_error!(set_file_times(&paths.part_path, now, addon_time),   |e| "Failed to set file time for path: {}",e);
-                    }
-                    if paths.url_txt_path.is_file() {
-                        log_error!(set_file_times(&paths.url_txt_path, now, addon_time),|e| "Failed to set file time url_txt_path: {}",e);
-                    }
-                }
+            if part_path != download_to {
+                std::fs::rename(&part_path, download_to)?;
             }
 
-            return Ok(finalizer);
+            return Ok(hashes.sha1);
         }
         Err(soft_error.unwrap())
     }
 
+    fn matches_hashes(&self, hashes: &StreamHashes) -> bool {
+        self.sha1_hash.as_ref().map_or(true, |h| *h == hashes.sha1 ) &&
+        self.sha256_hash.as_ref().map_or(true, |h| Some(h) == hashes.sha256.as_ref() ) &&
+        self.sha512_hash.as_ref().map_or(true, |h| Some(h) == hashes.sha512.as_ref() )
+    }
+
     pub fn write_url_txt(&self, paths: &FilePaths, conf: &Conf, api: &mut API, sha: &str) -> Result<Finalize,anyhow::Error> {
         // write .url.txt.part with file url and SHA1 hash
         let mut url_txt = vec![];
@@ -199,17 +284,23 @@ impl AddonFile {
         }
     }
 
+    /// name of the file in the shared cache dir
+    pub fn cache_file_name(&self, addon_id: AddonID) -> String {
+        format!("{}_{}_{}_{}",addon_id.source().prefix(),addon_id.0,self.id.0,self.file_name)
+    }
+
     pub fn file_paths_new(&self, addon_id: AddonID, disabled: bool, conf: &Conf) -> FilePaths {
         let paths = self.file_paths_part_new(disabled);
 
         let cache_path = conf.symlink_cache_path.as_ref().map(|cache_dir| {
-            cache_dir.join(format!("cf_{}_{}_{}",addon_id.0,self.id.0,self.file_name))
+            cache_dir.join(self.cache_file_name(addon_id))
         });
 
         FilePaths {
             path: paths.path,
             part_path: paths.part_path,
             cache_path,
+            cache_link_mode: conf.cache_link_mode,
             url_txt_path: paths.url_txt_path,
             disabled: paths.disabled,
         }
@@ -219,13 +310,14 @@ impl AddonFile {
         let paths = self.file_paths_part_current(allow_fixups);
 
         let cache_path = conf.symlink_cache_path.as_ref().map(|cache_dir| {
-            cache_dir.join(format!("cf_{}_{}_{}",addon_id.0,self.id.0,self.file_name))
+            cache_dir.join(self.cache_file_name(addon_id))
         });
 
         FilePaths {
             path: paths.path,
             part_path: paths.part_path,
             cache_path,
+            cache_link_mode: conf.cache_link_mode,
             url_txt_path: paths.url_txt_path,
             disabled: paths.disabled,
         }
@@ -236,6 +328,7 @@ pub struct FilePaths {
     pub path: PathBuf,
     pub part_path: PathBuf,
     pub cache_path: Option<PathBuf>,
+    pub cache_link_mode: CacheLinkMode,
     pub url_txt_path: PathBuf,
     pub disabled: bool,
 }
@@ -278,6 +371,17 @@ impl FilePaths {
     pub fn is_downloaded(&self) -> bool {
         self.path.is_file()
     }
+
+    /// rename existing addon file and .url.txt to the paths of new (e.g. for disable/enable)
+    pub fn rename_to(&self, new: &Self) -> anyhow::Result<()> {
+        if new.path != self.path && is_file_or_symlink(&self.path) {
+            std::fs::rename(&self.path, &new.path)?;
+        }
+        if new.url_txt_path != self.url_txt_path && is_file_or_symlink(&self.url_txt_path) {
+            std::fs::rename(&self.url_txt_path, &new.url_txt_path)?;
+        }
+        Ok(())
+    }
 }
 
 pub(super) fn file_write(p: impl AsRef<Path>) -> std::io::Result<File> {
@@ -287,6 +391,57 @@ pub(super) fn file_write(p: impl AsRef<Path>) -> std::io::Result<File> {
         .truncate(true)
         .open(p)
 }
+/// hashes the file while it is streamed, sha256 and sha512 only if the file has them
+struct StreamHasher {
+    len: u64,
+    sha1: Sha1,
+    sha256: Option<Sha256>,
+    sha512: Option<Sha512>,
+}
+
+struct StreamHashes {
+    sha1: String,
+    sha256: Option<String>,
+    sha512: Option<String>,
+}
+
+impl StreamHasher {
+    fn new(file: &AddonFile) -> Self {
+        Self {
+            len: 0,
+            sha1: Sha1::new(),
+            sha256: file.sha256_hash.is_some().then(Sha256::new),
+            sha512: file.sha512_hash.is_some().then(Sha512::new),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.len += data.len() as u64;
+        self.sha1.update(data);
+        if let Some(h) = &mut self.sha256 {h.update(data)}
+        if let Some(h) = &mut self.sha512 {h.update(data)}
+    }
+
+    fn finalize(self) -> StreamHashes {
+        StreamHashes {
+            sha1: hex::encode(&*self.sha1.finalize()),
+            sha256: self.sha256.map(|h| hex::encode(&*h.finalize()) ),
+            sha512: self.sha512.map(|h| hex::encode(&*h.finalize()) ),
+        }
+    }
+}
+
+impl Write for StreamHasher {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 pub(super) fn file_read(p: impl AsRef<Path>) -> std::io::Result<File> {
     OpenOptions::new()
         .read(true)
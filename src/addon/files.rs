@@ -1,5 +1,6 @@
 use std::borrow::Borrow;
 use std::convert::TryInto;
+use std::path::{Path, PathBuf};
 
 use furse::structures::file_structs::{File, HashAlgo};
 use serde_derive::*;
@@ -8,7 +9,9 @@ use crate::error;
 
 use super::{FileGameVersion, FileID};
 use super::dependency::Dependencies;
+use super::loader::{CF_MOD_LOADER_TYPE_ID, Loader};
 use super::release_type::ReleaseType;
+use super::source::Source;
 
 #[derive(Deserialize,Serialize,Clone)]
 #[serde(rename_all="camelCase")]
@@ -26,9 +29,16 @@ pub struct AddonFile {
     pub is_available: bool, //TODO handle is_available
     pub package_fingerprint: u32,
     pub game_version: Vec<FileGameVersion>,
+    /// empty in repo locks from before, see AddonFile::loaders
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub loaders: Vec<Loader>,
     pub has_install_script: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sha1_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha512_hash: Option<String>,
 }
 
 #[derive(Deserialize,Serialize,Clone)]
@@ -36,14 +46,25 @@ pub struct AddonFile {
 #[repr(transparent)]
 pub struct DownloadURL(pub String); //TODO TrimmedString
 
+impl DownloadURL {
+    /// vendored files of direct addons are referenced as file://path
+    pub fn local_path(&self) -> Option<&Path> {
+        self.0.trim().strip_prefix("file://").map(Path::new)
+    }
+    /// the vendored file resolved against the directory of the repo json, the path is relative to it
+    pub fn local_path_in(&self, repo_dir: &Path) -> Option<PathBuf> {
+        self.local_path().map(|p| repo_dir.join(p) )
+    }
+}
+
 #[allow(dead_code)]
-fn assert_memsize(a: AddonFile) -> [u8;200] {
+fn assert_memsize(a: AddonFile) -> [u8;272] {
     unsafe{
         std::mem::transmute(a)
     }
 }
 #[allow(dead_code)]
-fn assert_omemsize(a: Option<AddonFile>) -> [u8;200] {
+fn assert_omemsize(a: Option<AddonFile>) -> [u8;272] {
     unsafe{
         std::mem::transmute(a)
     }
@@ -53,6 +74,14 @@ impl AddonFile {
     pub fn sort_deps(&mut self) {
         self.dependencies.sort_unstable_by_key(|v| (v.idx(),v.id().0) )
     }
+
+    /// CurseForge file ids are ascending, Modrinth version ids are random and ordered by publish date
+    pub fn older_than(&self, other: &AddonFile) -> bool {
+        match (self.id.source(),other.id.source()) {
+            (Source::CurseForge,Source::CurseForge) => self.id.0 < other.id.0,
+            _ => (&self.file_date,self.id.0) < (&other.file_date,other.id.0),
+        }
+    }
 }
 
 impl Borrow<ReleaseType> for AddonFile {
@@ -67,6 +96,16 @@ impl From<File> for AddonFile {
             error!("{} is undistributable",file.file_name);
         }
 
+        let mut loaders: Vec<Loader> = vec![];
+        for l in file.sortable_game_versions.iter()
+            .filter(|v| v.game_version_type_id == Some(CF_MOD_LOADER_TYPE_ID) )
+            .filter_map(|v| Loader::from_name(&v.game_version_name) )
+        {
+            if !loaders.contains(&l) {
+                loaders.push(l);
+            }
+        }
+
         Self {
             id: FileID(file.id.try_into().unwrap()),
             display_name: file.display_name,
@@ -81,8 +120,11 @@ impl From<File> for AddonFile {
             dependencies: file.dependencies.into(),
             package_fingerprint: file.file_fingerprint as u32,
             game_version: file.game_versions.into_iter().map(FileGameVersion).collect(),
+            loaders,
             has_install_script: false, //TODO
             sha1_hash: file.hashes.into_iter().find(|h| h.algo == HashAlgo::Sha1 ).map(|h| h.value ),
+            sha256_hash: None,
+            sha512_hash: None,
         }
     }
 }
@@ -0,0 +1,76 @@
+use std::io::Read;
+use std::path::Path;
+
+use crate::addon::download::file_read;
+
+/// CurseForge package fingerprint, murmur2 (seed 1) over the file with whitespace bytes skipped
+pub fn fingerprint_file(file: impl AsRef<Path>) -> std::io::Result<u32> {
+    let mut buf = Vec::with_capacity(1024*1024);
+    file_read(&file)?.read_to_end(&mut buf)?;
+    Ok(fingerprint(&buf))
+}
+
+pub fn fingerprint(data: &[u8]) -> u32 {
+    let len = data.iter().filter(|&&b| !is_whitespace(b) ).count() as u32;
+    murmur2(data.iter().copied().filter(|&b| !is_whitespace(b) ), len, 1)
+}
+
+/// 32-bit MurmurHash2 over the bytes, len is their count
+fn murmur2(data: impl Iterator<Item=u8>, len: u32, seed: u32) -> u32 {
+    const M: u32 = 0x5bd1e995;
+    const R: u32 = 24;
+
+    let mut h: u32 = seed ^ len;
+    let mut k: u32 = 0;
+    let mut shift = 0;
+
+    for b in data {
+        k |= (b as u32) << shift;
+        shift += 8;
+        if shift == 32 {
+            k = k.wrapping_mul(M);
+            k ^= k >> R;
+            k = k.wrapping_mul(M);
+
+            h = h.wrapping_mul(M);
+            h ^= k;
+
+            k = 0;
+            shift = 0;
+        }
+    }
+
+    if shift != 0 {
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^= h >> 15;
+    h
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, 9 | 10 | 13 | 32)
+}
+
+#[test]
+fn test_fingerprint() {
+    // SMHasher verification of MurmurHash2: keys 0..i hashed with seed 256-i, the concatenated hashes hashed with seed 0
+    let mut hashes = Vec::with_capacity(1024);
+    for i in 0..256u32 {
+        let key: Vec<u8> = (0..i as u8).collect();
+        hashes.extend_from_slice(&murmur2(key.iter().copied(), i, 256-i).to_le_bytes());
+    }
+    assert_eq!(murmur2(hashes.iter().copied(), 1024, 0), 0x27864C1E);
+
+    // whitespace is skipped
+    assert_eq!(fingerprint(b"\thello\r\n world "), fingerprint(b"helloworld"));
+    assert_eq!(fingerprint(b"helloworld"), murmur2(b"helloworld".iter().copied(), 10, 1));
+
+    // fixed values of CurseForge's normalization: seed 1, only tab, LF, CR and space skipped (VT and FF are kept),
+    // hashed length is the filtered 29 bytes
+    assert_eq!(fingerprint(b"{\n\t\"name\": \"cursinator\",\r\n\x0b\x0c\"v\": 1\n}\n"), 249856824);
+    assert_eq!(fingerprint(b" \t\r\n"), 1540447798);
+}
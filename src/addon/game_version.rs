@@ -0,0 +1,199 @@
+use std::cmp::Ordering;
+use std::fmt::Display;
+
+use regex::Regex;
+use serde_derive::*;
+
+use super::FileGameVersion;
+use super::version_constraint::compare_versions;
+
+/// Game version setting of the repo, a single pattern or a list of patterns
+///
+/// A file matches if any of its game versions matches any pattern:
+/// - exact `1.20.1`
+/// - wildcard `1.20.x` or `1.*`, a `x`/`*` component matches any component, a trailing one also matches none
+/// - range `1.20.1..=1.20.4` or `1.20.1..1.20.5`, either end can be left open
+/// - regex `/^1\.20\.[1-4]$/`
+#[derive(Clone)]
+pub struct GameVersion {
+    patterns: Vec<Pattern>,
+}
+
+#[derive(Clone)]
+struct Pattern {
+    src: String,
+    kind: Kind,
+}
+
+#[derive(Clone)]
+enum Kind {
+    Exact(String),
+    /// None is a wildcard component
+    Wildcard(Vec<Option<String>>),
+    Range {
+        start: Option<Vec<u64>>,
+        end: Option<Vec<u64>>,
+        inclusive: bool,
+    },
+    Regex(Regex),
+}
+
+impl GameVersion {
+    pub fn new(patterns: impl IntoIterator<Item=impl Into<String>>) -> anyhow::Result<Self> {
+        let patterns = patterns.into_iter()
+            .map(|p| Pattern::parse(p.into()) )
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if patterns.is_empty() {
+            anyhow::bail!("No game version pattern");
+        }
+        Ok(Self { patterns })
+    }
+
+    pub fn matches<'a>(&self, mut gv: impl Iterator<Item=&'a FileGameVersion>) -> bool {
+        gv.any(|v| self.matches_str(&v.0) )
+    }
+    pub fn matches_idx<'a>(&self, gv: impl Iterator<Item=&'a FileGameVersion>) -> Option<usize> {
+        gv.enumerate().find(|(_,v)| self.matches_str(&v.0) ).map(|(i,_)| i )
+    }
+    pub fn matches_str(&self, v: &str) -> bool {
+        let v = v.trim();
+        self.patterns.iter().any(|p| p.matches(v) )
+    }
+
+    /// the first exact version, e.g. for the modpack manifest, which can't take patterns
+    pub fn exact(&self) -> Option<&str> {
+        self.patterns.iter()
+            .find_map(|p| match &p.kind {
+                Kind::Exact(v) => Some(&**v),
+                _ => None,
+            })
+    }
+}
+
+impl Pattern {
+    fn parse(src: String) -> anyhow::Result<Self> {
+        let s = src.trim();
+
+        let kind = if let Some(re) = s.strip_prefix('/').and_then(|s| s.strip_suffix('/') ) {
+            Kind::Regex(
+                Regex::new(re).map_err(|e| anyhow::anyhow!("Invalid game version regex {}: {}",s,e) )?
+            )
+        } else if let Some((start,end)) = s.split_once("..") {
+            let (end,inclusive) = match end.strip_prefix('=') {
+                Some(end) => (end,true),
+                None => (end,false),
+            };
+            let bound = |b: &str| -> anyhow::Result<Option<Vec<u64>>> {
+                let b = b.trim();
+                if b.is_empty() {return Ok(None);}
+                parse_numeric(b).map(Some).ok_or_else(|| anyhow::anyhow!("Invalid game version range bound {} in {}",b,s) )
+            };
+            Kind::Range { start: bound(start)?, end: bound(end)?, inclusive }
+        } else if s.split('.').any(|c| c == "x" || c == "*" ) {
+            Kind::Wildcard(
+                s.split('.')
+                    .map(|c| (c != "x" && c != "*").then(|| c.to_owned() ) )
+                    .collect()
+            )
+        } else if !s.is_empty() && !s.contains(['*','?','[','(','|']) {
+            Kind::Exact(s.to_owned())
+        } else {
+            anyhow::bail!("Invalid game version pattern: {:?}",s);
+        };
+
+        Ok(Self { src, kind })
+    }
+
+    fn matches(&self, v: &str) -> bool {
+        match &self.kind {
+            Kind::Exact(e) => e == v,
+            Kind::Wildcard(w) => {
+                let c: Vec<&str> = v.split('.').collect();
+                let trailing = w.last().map_or(false, Option::is_none);
+                let fixed = if trailing {w.len()-1} else {w.len()};
+                if c.len() < fixed || (!trailing && c.len() != fixed) {
+                    return false;
+                }
+                w[..fixed].iter().zip(&c).all(|(w,c)| w.as_ref().map_or(true, |w| w == c ) )
+            },
+            Kind::Range { start, end, inclusive } => {
+                let v = match parse_numeric(v) {
+                    Some(v) => v,
+                    None => return false,
+                };
+                start.as_ref().map_or(true, |s| compare_versions(&v, s) != Ordering::Less ) &&
+                end.as_ref().map_or(true, |e| match compare_versions(&v, e) {
+                    Ordering::Less => true,
+                    Ordering::Equal => *inclusive,
+                    Ordering::Greater => false,
+                })
+            },
+            Kind::Regex(r) => r.is_match(v),
+        }
+    }
+}
+
+fn parse_numeric(s: &str) -> Option<Vec<u64>> {
+    s.split('.').map(|c| c.parse().ok() ).collect()
+}
+
+impl Display for GameVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let src: Vec<&str> = self.patterns.iter().map(|p| p.src.trim() ).collect();
+        write!(f, "{}", src.join(", "))
+    }
+}
+
+#[derive(Deserialize,Serialize)]
+#[serde(untagged)]
+enum Repr {
+    One(String),
+    List(Vec<String>),
+}
+
+/// a single pattern is stored as string, like before lists were supported
+impl serde::Serialize for GameVersion {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer {
+        match &self.patterns[..] {
+            [p] => Repr::One(p.src.clone()),
+            p => Repr::List(p.iter().map(|p| p.src.clone() ).collect()),
+        }.serialize(serializer)
+    }
+}
+impl<'de> serde::Deserialize<'de> for GameVersion {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de> {
+        let patterns = match Repr::deserialize(deserializer)? {
+            Repr::One(p) => vec![p],
+            Repr::List(p) => p,
+        };
+        Self::new(patterns).map_err(serde::de::Error::custom)
+    }
+}
+
+#[test]
+fn test_game_version() {
+    let gv = GameVersion::new(["1.16.x"]).unwrap();
+    assert!(gv.matches_str("1.16"));
+    assert!(gv.matches_str("1.16.5"));
+    assert!(!gv.matches_str("1.17.1"));
+
+    let gv = GameVersion::new(["1.20.1..=1.20.4","/^23w\\d+a$/","1.x.2"]).unwrap();
+    assert!(gv.matches_str("1.20.1"));
+    assert!(gv.matches_str("1.20.4"));
+    assert!(!gv.matches_str("1.20.5"));
+    assert!(!gv.matches_str("1.20"));
+    assert!(gv.matches_str("23w13a"));
+    assert!(gv.matches_str("1.18.2"));
+    assert!(!gv.matches_str("1.18.2.1"));
+    assert!(!gv.matches_str("Forge"));
+    assert_eq!(gv.exact(), None);
+    assert_eq!(GameVersion::new(["1.20.x","1.20.1"]).unwrap().exact(), Some("1.20.1"));
+
+    assert!(GameVersion::new(["/(/"]).is_err());
+    assert!(GameVersion::new(["1.a..1.2"]).is_err());
+    assert!(GameVersion::new(Vec::<String>::new()).is_err());
+}
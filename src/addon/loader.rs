@@ -0,0 +1,97 @@
+use std::fmt::Display;
+
+use furse::structures::file_structs::ModLoaderType;
+use serde_derive::*;
+
+use crate::conf::Conf;
+
+use super::files::AddonFile;
+
+/// Mod loader of the repo
+#[derive(Deserialize,Serialize,Clone,Copy,PartialEq,Eq,Debug,clap::ValueEnum)]
+#[serde(rename_all="lowercase")]
+pub enum Loader {
+    Forge,
+    #[value(name="neoforge")]
+    NeoForge,
+    Fabric,
+    Quilt,
+}
+
+/// CurseForge game version type of the mod loader entries in sortableGameVersions
+pub const CF_MOD_LOADER_TYPE_ID: i32 = 68441;
+
+impl Loader {
+    pub fn from_name(s: &str) -> Option<Self> {
+        match &*s.trim().to_lowercase() {
+            "forge" => Some(Self::Forge),
+            "neoforge" => Some(Self::NeoForge),
+            "fabric" => Some(Self::Fabric),
+            "quilt" => Some(Self::Quilt),
+            _ => None,
+        }
+    }
+
+    pub fn from_cf(t: &ModLoaderType) -> Option<Self> {
+        match t {
+            ModLoaderType::Forge => Some(Self::Forge),
+            ModLoaderType::NeoForge => Some(Self::NeoForge),
+            ModLoaderType::Fabric => Some(Self::Fabric),
+            ModLoaderType::Quilt => Some(Self::Quilt),
+            _ => None,
+        }
+    }
+
+    /// loaders of which files also work with this loader
+    pub fn default_fallbacks(&self) -> Vec<Self> {
+        match self {
+            Self::Quilt => vec![Self::Fabric],
+            _ => vec![],
+        }
+    }
+}
+
+impl Display for Loader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Forge => write!(f,"forge"),
+            Self::NeoForge => write!(f,"neoforge"),
+            Self::Fabric => write!(f,"fabric"),
+            Self::Quilt => write!(f,"quilt"),
+        }
+    }
+}
+
+impl AddonFile {
+    /// loaders of the file, files in repo locks from before the loader field have them in the game versions
+    pub fn loaders(&self) -> Vec<Loader> {
+        if !self.loaders.is_empty() {
+            return self.loaders.clone();
+        }
+        self.game_version.iter()
+            .filter_map(|v| Loader::from_name(&v.0) )
+            .collect()
+    }
+}
+
+impl Conf {
+    /// files without known loader are always accepted
+    pub fn accepts_loaders(&self, loaders: &[Loader]) -> bool {
+        let Some(loader) = self.loader else {return true};
+        loaders.is_empty() ||
+            loaders.iter().any(|l| *l == loader || self.loader_fallbacks.contains(l) )
+    }
+
+}
+
+#[test]
+fn test_accepts_loaders() {
+    let mut conf = crate::cmd::init::new_repo(super::GameVersion::new(["1.20.1"]).unwrap()).conf;
+    assert!(conf.accepts_loaders(&[Loader::Forge]));
+
+    conf.loader = Some(Loader::Quilt);
+    conf.loader_fallbacks = Loader::Quilt.default_fallbacks();
+    assert!(conf.accepts_loaders(&[Loader::Fabric]));
+    assert!(!conf.accepts_loaders(&[Loader::Forge]));
+    assert!(conf.accepts_loaders(&[]));
+}
@@ -4,6 +4,7 @@ use std::ops::{Deref, DerefMut};
 use super::*;
 use super::files::AddonFile;
 use super::rtm::ReleaseTypeMode;
+use super::version_constraint::VersionConstraint;
 
 #[derive(Deserialize,Serialize)]
 pub struct LocalAddon { //TODO defaults
@@ -16,7 +17,18 @@ pub struct LocalAddon { //TODO defaults
     pub version_blacklist: Option<String>, //blacklist versions with occurrence in game versions or filename
     #[serde(default = "default_positive_negative_in_filename")]
     pub positive_negative_in_filename: bool,
+    /// stored in the repo lock, only read from repo json for repos without lock
+    #[serde(default, skip_serializing)]
     pub installed: Option<AddonFile>,
+    /// addon file is renamed to .disabled, will be kept on update and download-all
+    #[serde(default)]
+    pub disabled: bool,
+    /// optional dependencies which were listed on install and not installed, they aren't listed again
+    #[serde(default)]
+    pub declined_optionals: Vec<AddonID>,
+    /// only versions in this range are installed, like >=7.6, <8
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_constraint: Option<VersionConstraint>,
 }
 
 fn default_positive_negative_in_filename() -> bool {
@@ -5,9 +5,16 @@ pub mod local;
 pub mod download;
 pub mod rtm;
 pub mod validate;
+pub mod fingerprint;
+pub mod source;
+pub mod version_constraint;
+pub mod game_version;
+pub mod loader;
 
 use std::fmt::Display;
 
+pub use game_version::GameVersion;
+
 use serde::{Deserialize,Serialize};
 
 #[derive(Deserialize,Serialize,Copy,Clone,PartialEq,Eq,Hash)]
@@ -38,63 +45,6 @@ pub struct FileID(pub u64);
 #[repr(transparent)]
 pub struct FileGameVersion(pub String);
 
-#[derive(Deserialize,Serialize,Clone)]
-#[serde(transparent)]
-#[repr(transparent)]
-pub struct GameVersion(pub String);
-
-impl PartialEq<GameVersion> for FileGameVersion {
-    fn eq(&self, other: &GameVersion) -> bool {
-        other.eq(self)
-    }
-}
-impl PartialEq<FileGameVersion> for GameVersion {
-    fn eq(&self, other: &FileGameVersion) -> bool {
-        let s = self.0.trim();
-        let other = other.0.trim();
-        let mut s = s.splitn(2,'x');
-
-        let start = s.next().expect("Invalid game version pattern");
-        let mut start_stripped = start;
-        if let Some(s) = start.strip_suffix('.') {
-            start_stripped = s;
-        }
-        let end = s.next();
-
-        if s.next().is_some() {
-            panic!("Invalid game version pattern");
-        }
-
-        if let Some(end) = end {
-            (other.starts_with(start) && other.ends_with(end)) || eq_str_concat(other, [start_stripped,end])
-        } else {
-            other == start
-        }
-    }
-}
-
-fn eq_str_concat<const N: usize>(a: &str, other: [&str;N]) -> bool {
-    let mut a = a.as_bytes();
-    for b in other {
-        let b = b.as_bytes();
-        if a.starts_with(b) {
-            a = unsafe { a.get_unchecked(..b.len()) };
-        } else {
-            return false;
-        }
-    }
-    a.is_empty()
-}
-
-impl GameVersion {
-    pub fn matches<'a>(&self, mut gv: impl Iterator<Item=&'a FileGameVersion>) -> bool {
-        gv.any(|v| self == v )
-    }
-    pub fn matches_idx<'a>(&self, gv: impl Iterator<Item=&'a FileGameVersion>) -> Option<usize> {
-        gv.enumerate().find(|(_,v)| &self == v ).map(|(i,_)| i )
-    }
-}
-
 impl PartialEq for AddonSlug {
     fn eq(&self, other: &Self) -> bool {
         self.0.trim() == other.0.trim()
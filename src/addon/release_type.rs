@@ -22,11 +22,14 @@ impl From<FileReleaseType> for ReleaseType {
 
 impl ReleaseType {
     pub fn from_number(i: u32) -> Self {
+        Self::try_from_number(i).unwrap_or_else(|| panic!("Unknown ReleaseType {}",i))
+    }
+    pub fn try_from_number(i: u32) -> Option<Self> {
         match i {
-            1 => Self::Release,
-            2 => Self::Beta,
-            3 => Self::Alpha,
-            _ => panic!("Unknown ReleaseType {}",i),
+            1 => Some(Self::Release),
+            2 => Some(Self::Beta),
+            3 => Some(Self::Alpha),
+            _ => None,
         }
     }
     pub fn to_number(&self) -> u32 {
@@ -68,8 +71,9 @@ impl<'de> serde::Deserialize<'de> for ReleaseType {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de> {
-        u32::deserialize(deserializer)
-            .map(Self::from_number)
+        let i = u32::deserialize(deserializer)?;
+        Self::try_from_number(i)
+            .ok_or_else(|| serde::de::Error::custom(format!("Unknown release type {}",i)) )
     }
 }
 
@@ -7,6 +7,7 @@ use crate::conf::Conf;
 use super::GameVersion;
 use super::files::AddonFile;
 use super::release_type::ReleaseType;
+use super::version_constraint::VersionConstraint;
 
 /// Examples
 /// release | beta | alpha | result
@@ -43,34 +44,61 @@ impl ReleaseTypeMode {
             ReleaseType::Alpha   => self.alpha,
         }
     }
-    pub fn pick_version<'a>(&self, v: &'a [AddonFile], conf: &Conf, blacklist: Option<&str>, positive_negative_in_filename: bool) -> Option<&'a AddonFile> {
-        fn find_legal<'a>(v: &'a [AddonFile], g: ReleaseType, conf: &Conf, blacklist: Option<&str>, positive_negative_in_filename: bool) -> Option<&'a AddonFile> {
+    pub fn pick_version<'a>(&self, v: &'a [AddonFile], conf: &Conf, blacklist: Option<&str>, positive_negative_in_filename: bool, constraint: Option<&VersionConstraint>) -> Option<&'a AddonFile> {
+        fn find_legal<'a>(v: &'a [AddonFile], g: ReleaseType, conf: &Conf, blacklist: Option<&str>, positive_negative_in_filename: bool, constraint: Option<&VersionConstraint>) -> Option<&'a AddonFile> {
             v.iter()
                 .rev()
                 .filter(|file| conf.filter_addon_file(file, blacklist, positive_negative_in_filename))
+                .filter(|file| constraint.map_or(true, |c| c.matches(file) ))
                 .find(|v| v.release_type >= g )
         }
 
         let mut r = None;
 
         if r.is_none() && self.release {
-            r = find_legal(v, ReleaseType::Release, conf, blacklist, positive_negative_in_filename);
+            r = find_legal(v, ReleaseType::Release, conf, blacklist, positive_negative_in_filename, constraint);
         }
         if r.is_none() && self.beta {
-            r = find_legal(v, ReleaseType::Beta   , conf, blacklist, positive_negative_in_filename);
+            r = find_legal(v, ReleaseType::Beta   , conf, blacklist, positive_negative_in_filename, constraint);
         }
         if r.is_none() && self.alpha {
-            r = find_legal(v, ReleaseType::Alpha  , conf, blacklist, positive_negative_in_filename);
+            r = find_legal(v, ReleaseType::Alpha  , conf, blacklist, positive_negative_in_filename, constraint);
         }
 
         if r.is_none() {
             r = v.iter()
                 .filter(|file| conf.filter_addon_file(file, blacklist, positive_negative_in_filename))
+                .filter(|file| constraint.map_or(true, |c| c.matches(file) ))
                 .last();
         }
 
         r
     }
+    /// all legal versions in order of preference, the first one is the one pick_version would pick
+    pub fn candidates<'a>(&self, v: &'a [AddonFile], conf: &Conf, blacklist: Option<&str>, positive_negative_in_filename: bool, constraint: Option<&VersionConstraint>) -> Vec<&'a AddonFile> {
+        let legal: Vec<&AddonFile> = v.iter()
+            .rev()
+            .filter(|file| conf.filter_addon_file(file, blacklist, positive_negative_in_filename))
+            .filter(|file| constraint.map_or(true, |c| c.matches(file) ))
+            .collect();
+
+        let mut dest: Vec<&AddonFile> = Vec::with_capacity(legal.len());
+
+        for (enabled,g) in [(self.release,ReleaseType::Release),(self.beta,ReleaseType::Beta),(self.alpha,ReleaseType::Alpha)] {
+            if !enabled {continue;}
+            for &file in &legal {
+                if file.release_type >= g && !dest.iter().any(|d| d.id == file.id ) {
+                    dest.push(file);
+                }
+            }
+        }
+        // like pick_version, files outside the channel only if there are none in it
+        if dest.is_empty() {
+            dest = legal;
+        }
+
+        dest
+    }
     pub fn pick_level(&self, v: impl Iterator<Item=ReleaseType>+DoubleEndedIterator) -> ReleaseType {
         let (
             mut alpha_found,
@@ -122,6 +150,10 @@ impl Conf {
             return false;
         }
 
+        if !self.accepts_loaders(&file.loaders()) {
+            return false;
+        }
+
         fn in_list(file: &AddonFile, entry: &str) -> bool {
             let entry = entry.trim().to_lowercase();
             for file_game_version in &file.game_version {
@@ -0,0 +1,161 @@
+use std::fmt::Display;
+
+use super::{AddonID, FileID};
+
+/// Modrinth ids are base62 encoded u64, stored with this bit set to not collide with CurseForge ids
+const MODRINTH_FLAG: u64 = 1 << 63;
+/// ids of direct addons, which are local to the repo
+const DIRECT_FLAG: u64 = 1 << 62;
+
+const BASE62: &[u8;62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum Source {
+    CurseForge,
+    Modrinth,
+    /// URL or vendored file, not tracked by any platform
+    Direct,
+}
+
+impl Source {
+    fn of(id: u64) -> Self {
+        if id & MODRINTH_FLAG != 0 {
+            Self::Modrinth
+        } else if id & DIRECT_FLAG != 0 {
+            Self::Direct
+        } else {
+            Self::CurseForge
+        }
+    }
+
+    /// prefix of cache files and addon queries
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            Self::CurseForge => "cf",
+            Self::Modrinth => "mr",
+            Self::Direct => "direct",
+        }
+    }
+
+    /// strip source prefix like "mr:sodium" from addon query
+    pub fn from_query(query: &str) -> (Self,&str) {
+        if let Some(q) = query.strip_prefix("mr:").or_else(|| query.strip_prefix("modrinth:") ) {
+            (Self::Modrinth,q)
+        } else if let Some(q) = query.strip_prefix("cf:").or_else(|| query.strip_prefix("curseforge:") ) {
+            (Self::CurseForge,q)
+        } else {
+            (Self::CurseForge,query)
+        }
+    }
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CurseForge => f.write_str("CurseForge"),
+            Self::Modrinth => f.write_str("Modrinth"),
+            Self::Direct => f.write_str("Direct"),
+        }
+    }
+}
+
+/// source-prefixed id like mr:AANobbMI, for messages
+impl Display for AddonID {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.source() {
+            Source::CurseForge => write!(f, "cf:{}", self.0),
+            Source::Modrinth => write!(f, "mr:{}", self.modrinth_id()),
+            Source::Direct => write!(f, "direct:{}", self.direct_n()),
+        }
+    }
+}
+
+/// source-prefixed id like cf:3456789, for messages and machine-readable output
+impl Display for FileID {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.source() {
+            Source::CurseForge => write!(f, "cf:{}", self.0),
+            Source::Modrinth => write!(f, "mr:{}", self.modrinth_id()),
+            Source::Direct => write!(f, "direct:{:x}", self.0 & !DIRECT_FLAG),
+        }
+    }
+}
+
+impl AddonID {
+    pub fn source(&self) -> Source {
+        Source::of(self.0)
+    }
+    pub fn from_modrinth(id: &str) -> Option<Self> {
+        decode_base62(id).map(|v| Self(v | MODRINTH_FLAG) )
+    }
+    pub fn modrinth_id(&self) -> String {
+        debug_assert_eq!(self.source(), Source::Modrinth);
+        encode_base62(self.0 & !MODRINTH_FLAG)
+    }
+}
+
+impl AddonID {
+    /// n-th direct addon of the repo
+    pub fn direct(n: u64) -> Self {
+        Self(n | DIRECT_FLAG)
+    }
+    pub fn direct_n(&self) -> u64 {
+        debug_assert_eq!(self.source(), Source::Direct);
+        self.0 & !DIRECT_FLAG
+    }
+}
+
+impl FileID {
+    pub fn source(&self) -> Source {
+        Source::of(self.0)
+    }
+    pub fn from_modrinth(id: &str) -> Option<Self> {
+        decode_base62(id).map(|v| Self(v | MODRINTH_FLAG) )
+    }
+    pub fn modrinth_id(&self) -> String {
+        debug_assert_eq!(self.source(), Source::Modrinth);
+        encode_base62(self.0 & !MODRINTH_FLAG)
+    }
+}
+
+impl FileID {
+    /// derived from the pinned hash of the direct addon file
+    pub fn direct(hash: &[u8]) -> Self {
+        let mut v = [0u8;8];
+        let n = hash.len().min(8);
+        v[..n].copy_from_slice(&hash[..n]);
+        Self((u64::from_le_bytes(v) & !(MODRINTH_FLAG | DIRECT_FLAG)) | DIRECT_FLAG)
+    }
+}
+
+fn decode_base62(s: &str) -> Option<u64> {
+    if s.is_empty() {return None;}
+    let mut v: u64 = 0;
+    for c in s.bytes() {
+        let digit = BASE62.iter().position(|&b| b == c )? as u64;
+        v = v.checked_mul(62)?.checked_add(digit)?;
+    }
+    (v & MODRINTH_FLAG == 0).then_some(v)
+}
+
+fn encode_base62(mut v: u64) -> String {
+    let mut s = vec![];
+    loop {
+        s.push(BASE62[(v % 62) as usize]);
+        v /= 62;
+        if v == 0 {break}
+    }
+    s.reverse();
+    String::from_utf8(s).unwrap()
+}
+
+#[test]
+fn test_modrinth_id() {
+    let id = AddonID::from_modrinth("AANobbMI").unwrap();
+    assert_eq!(id.source(), Source::Modrinth);
+    assert_eq!(id.modrinth_id(), "AANobbMI");
+    assert_eq!(AddonID(238222).source(), Source::CurseForge);
+    assert_eq!(AddonID::direct(3).source(), Source::Direct);
+    assert_eq!(AddonID::direct(3).direct_n(), 3);
+    assert_eq!(FileID::direct(&[0xff;20]).source(), Source::Direct);
+}
@@ -2,6 +2,7 @@ use std::io::{Read, ErrorKind, BufRead};
 use std::path::Path;
 
 use sha1::{Sha1, Digest};
+use sha2::Sha256;
 
 use crate::addon::download::file_read;
 use crate::api::API;
@@ -9,6 +10,7 @@ use crate::conf::Conf;
 use crate::util::fs::Finalize;
 
 use super::download::FilePaths;
+use super::fingerprint::fingerprint_file;
 use super::files::AddonFile;
 
 impl AddonFile {
@@ -49,19 +51,24 @@ impl AddonFile {
             }
         }
 
-        if file_hash.is_none() && !result.urltxt_valid {
-            file_hash = Some(String::new());
-        }
-
         if result.file_exist && self.file_length == file.metadata()?.len() {
             let sha = sha1_hash_file(file)?;
 
             let sha_str = hex::encode(sha);
 
-            result.file_valid = &sha_str == file_hash.as_ref().unwrap();
+            result.file_valid = self.hashes_valid(file, &sha_str, file_hash.as_ref())?;
+
+            if file_hash.is_none() && result.file_valid {
+                file_hash = Some(sha_str);
+            }
+        }
+
+        // the file must also be placed from the cache as the link mode requires, e.g. after the mode changed
+        if let (Some(cache_path),false) = (&paths.cache_path,cache_only) {
+            result.file_valid &= paths.cache_link_mode.is_linked(cache_path, &paths.path);
         }
 
-        result.sha = file_hash.unwrap();
+        result.sha = file_hash.unwrap_or_default();
 
         Ok(result)
     }
@@ -85,16 +92,29 @@ impl AddonFile {
         
         if self.file_length != metadata.len() {return Ok(None);}
 
-        let file_hash = match self.sha1_hash.as_ref() {
-            Some(v) => v,
-            None => return Ok(None),
-        };
-
         let sha = sha1_hash_file(path)?;
 
         let sha_str = hex::encode(sha);
 
-        Ok((&sha_str == file_hash).then_some(sha_str))
+        let valid = self.hashes_valid(path, &sha_str, self.sha1_hash.as_ref())?;
+
+        Ok(valid.then_some(sha_str))
+    }
+
+    /// a pinned sha256 is always checked, in addition to the sha1 if known
+    fn hashes_valid(&self, path: &Path, sha_str: &str, sha1_hash: Option<&String>) -> Result<bool,anyhow::Error> {
+        if let Some(sha256_hash) = &self.sha256_hash {
+            if &sha256_hash_file(path)? != sha256_hash {
+                return Ok(false);
+            }
+        }
+
+        Ok(match sha1_hash {
+            Some(sha1_hash) => sha_str == sha1_hash,
+            None if self.sha256_hash.is_some() => true,
+            // no hash known, fall back to the package fingerprint
+            None => fingerprint_file(path)? == self.package_fingerprint,
+        })
     }
 
     /// validate and re-download if not valid
@@ -116,11 +136,19 @@ impl AddonFile {
 }
 
 fn sha1_hash_file(file: impl AsRef<Path>) -> std::io::Result<[u8;20]> {
+    Ok(hash_file::<Sha1>(file)?.into())
+}
+
+fn sha256_hash_file(file: impl AsRef<Path>) -> std::io::Result<String> {
+    Ok(hex::encode(hash_file::<Sha256>(file)?))
+}
+
+fn hash_file<D: Digest>(file: impl AsRef<Path>) -> std::io::Result<sha1::digest::Output<D>> {
     let mut buf = vec![0u8;65536];
 
     let mut mod_file = file_read(&file)?;
     
-    let mut hasher = Sha1::new();
+    let mut hasher = D::new();
 
     loop {
         match mod_file.read(&mut buf) {
@@ -134,15 +162,13 @@ fn sha1_hash_file(file: impl AsRef<Path>) -> std::io::Result<[u8;20]> {
         }
     }
 
-    let sha = hasher.finalize();
-
-    Ok(sha.into())
+    Ok(hasher.finalize())
 }
 
 pub struct ValidateResult {
     sha: String,
     file_exist: bool,
-    file_valid: bool,
+    pub file_valid: bool,
     urltxt_exist: bool,
     urltxt_valid: bool,
 }
@@ -0,0 +1,227 @@
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use super::files::AddonFile;
+
+/// Version range of an addon like `>=7.6, <8`, matched against the version extracted from the display or file name
+///
+/// Comparators are separated by `,` and all must match. A comparator without operator like `7` or `7.x` matches the version prefix.
+#[derive(Clone,PartialEq,Debug)]
+pub struct VersionConstraint {
+    comparators: Vec<Comparator>,
+}
+
+#[derive(Clone,PartialEq,Debug)]
+struct Comparator {
+    op: Op,
+    version: Vec<u64>,
+}
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    /// 7.x
+    Prefix,
+}
+
+impl VersionConstraint {
+    pub fn matches(&self, file: &AddonFile) -> bool {
+        match extract_version(file) {
+            Some(v) => self.matches_version(&v),
+            // files without recognizable version are never picked by a constraint
+            None => false,
+        }
+    }
+
+    fn matches_version(&self, v: &[u64]) -> bool {
+        self.comparators.iter().all(|c| {
+            let ord = compare_versions(v, &c.version);
+            match c.op {
+                Op::Eq => ord == Ordering::Equal,
+                Op::Ne => ord != Ordering::Equal,
+                Op::Gt => ord == Ordering::Greater,
+                Op::Ge => ord != Ordering::Less,
+                Op::Lt => ord == Ordering::Less,
+                Op::Le => ord != Ordering::Greater,
+                Op::Prefix => v.len() >= c.version.len() && v[..c.version.len()] == c.version[..],
+            }
+        })
+    }
+}
+
+impl FromStr for VersionConstraint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut comparators = vec![];
+
+        for part in s.split(',') {
+            let part = part.trim();
+            if part.is_empty() {continue;}
+
+            let (op,rest) = [
+                (">=",Op::Ge),("<=",Op::Le),("!=",Op::Ne),("==",Op::Eq),
+                (">",Op::Gt),("<",Op::Lt),("=",Op::Eq),
+            ].iter()
+                .find_map(|(p,op)| part.strip_prefix(p).map(|rest| (*op,rest.trim()) ) )
+                .unwrap_or((Op::Prefix,part));
+
+            let rest = rest.trim_start_matches(['v','V']);
+            let (rest,wildcard) = match rest.strip_suffix(".x").or_else(|| rest.strip_suffix(".*") ) {
+                Some(r) => (r,true),
+                None => (rest,false),
+            };
+            if wildcard && op != Op::Prefix {
+                anyhow::bail!("Wildcard only allowed without operator: {}",part);
+            }
+
+            let version = rest.split('.')
+                .map(|n| n.parse::<u64>() )
+                .collect::<Result<Vec<u64>,_>>()
+                .map_err(|_| anyhow::anyhow!("Invalid version in constraint: {}",part) )?;
+
+            comparators.push(Comparator { op, version });
+        }
+
+        if comparators.is_empty() {
+            anyhow::bail!("Empty version constraint");
+        }
+
+        Ok(Self { comparators })
+    }
+}
+
+impl Display for VersionConstraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i,c) in self.comparators.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            let op = match c.op {
+                Op::Eq => "=",
+                Op::Ne => "!=",
+                Op::Gt => ">",
+                Op::Ge => ">=",
+                Op::Lt => "<",
+                Op::Le => "<=",
+                Op::Prefix => "",
+            };
+            let version: Vec<String> = c.version.iter().map(|n| n.to_string() ).collect();
+            write!(f, "{}{}", op, version.join("."))?;
+            if c.op == Op::Prefix {
+                write!(f, ".x")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl serde::Serialize for VersionConstraint {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer {
+        self.to_string().serialize(serializer)
+    }
+}
+impl<'de> serde::Deserialize<'de> for VersionConstraint {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// missing components count as 0, so 8 == 8.0
+pub fn compare_versions(a: &[u64], b: &[u64]) -> Ordering {
+    for i in 0..a.len().max(b.len()) {
+        let ord = a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0));
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    Ordering::Equal
+}
+
+/// version of the addon file, from display name or file name
+///
+/// the first dotted number which isn't one of the game versions of the file, e.g. 11.5.0 from jei-1.19.2-forge-11.5.0.297.jar
+pub fn extract_version(file: &AddonFile) -> Option<Vec<u64>> {
+    let game_versions: Vec<Vec<u64>> = file.game_version.iter()
+        .filter_map(|g| parse_dotted(&g.0) )
+        .collect();
+
+    for name in [&file.display_name,&file.file_name] {
+        let candidates = dotted_numbers(name);
+        let found = candidates.iter()
+            .find(|v| v.len() >= 2 && !game_versions.contains(v) )
+            .or_else(|| candidates.iter().find(|v| !game_versions.contains(v) ) );
+        if let Some(v) = found {
+            return Some(v.clone());
+        }
+    }
+    None
+}
+
+fn parse_dotted(s: &str) -> Option<Vec<u64>> {
+    let v = dotted_numbers(s);
+    (v.len() == 1).then(|| v.into_iter().next().unwrap() )
+}
+
+/// runs of digits separated by single dots
+fn dotted_numbers(s: &str) -> Vec<Vec<u64>> {
+    let mut dest = vec![];
+    let mut current: Vec<u64> = vec![];
+    let mut number: Option<u64> = None;
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if let Some(d) = c.to_digit(10) {
+            number = Some(number.unwrap_or(0).saturating_mul(10).saturating_add(d as u64));
+            continue;
+        }
+        if let Some(n) = number.take() {
+            current.push(n);
+            if c == '.' && chars.peek().map_or(false, |c| c.is_ascii_digit() ) {
+                continue;
+            }
+        }
+        if !current.is_empty() {
+            dest.push(std::mem::take(&mut current));
+        }
+    }
+    if let Some(n) = number {
+        current.push(n);
+    }
+    if !current.is_empty() {
+        dest.push(current);
+    }
+
+    dest
+}
+
+#[test]
+fn test_version_constraint() {
+    let c: VersionConstraint = ">=7.6, <8".parse().unwrap();
+    assert!(c.matches_version(&[7,6]));
+    assert!(c.matches_version(&[7,9,1]));
+    assert!(!c.matches_version(&[7,5,9]));
+    assert!(!c.matches_version(&[8]));
+    assert_eq!(c.to_string(), ">=7.6, <8");
+
+    let c: VersionConstraint = "7.x".parse().unwrap();
+    assert!(c.matches_version(&[7,1]));
+    assert!(!c.matches_version(&[8,0]));
+
+    assert!("abc".parse::<VersionConstraint>().is_err());
+    assert!(">7.x".parse::<VersionConstraint>().is_err());
+
+    assert_eq!(dotted_numbers("jei-1.19.2-forge-11.5.0.297.jar"), vec![vec![1,19,2],vec![11,5,0,297]]);
+    assert_eq!(parse_dotted("1.19.2"), Some(vec![1,19,2]));
+}
@@ -0,0 +1,126 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use sha1::{Digest, Sha1};
+
+use crate::addon::AddonID;
+use crate::conf::Conf;
+use crate::util::fs::{part_file_path, remove_if};
+use crate::{dark_log, log_error};
+
+/// Persistent cache of API responses, stored as json files
+pub struct ApiCache {
+    dir: Option<PathBuf>,
+    ttl: Duration,
+    /// skip reading fresh entries, they are still written
+    refresh: bool,
+}
+
+impl ApiCache {
+    pub fn new(conf: &Conf, refresh: bool) -> Self {
+        Self {
+            dir: conf.api_cache_path.clone().or_else(default_api_cache_dir),
+            ttl: Duration::from_secs(conf.api_cache_ttl),
+            refresh,
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self {
+            dir: None,
+            ttl: Duration::ZERO,
+            refresh: false,
+        }
+    }
+
+    /// read a cache entry, stale entries are only used in offline mode
+    pub fn get<T: DeserializeOwned>(&self, kind: &str, key: &str, offline: bool) -> Option<T> {
+        if self.refresh && !offline {return None;}
+        let path = self.path(kind, key)?;
+
+        let age = path.metadata()
+            .and_then(|m| m.modified() ).ok()
+            .map(|t| SystemTime::now().duration_since(t).unwrap_or(Duration::ZERO) )?;
+        if !offline && age > self.ttl {return None;}
+
+        let data = std::fs::read(&path).ok()?;
+        match serde_jsonrc::from_slice(&data) {
+            Ok(v) => {
+                dark_log!("API: Cached {} {}",kind,key);
+                Some(v)
+            },
+            Err(e) => {
+                dark_log!("Invalid API cache entry {}: {}",path.to_string_lossy(),e);
+                None
+            },
+        }
+    }
+
+    pub fn put<T: Serialize>(&self, kind: &str, key: &str, value: &T) {
+        if let Some(path) = self.path(kind, key) {
+            log_error!(write_entry(&path, value),|e|"Failed to write API cache: {}",e);
+        }
+    }
+
+    fn path(&self, kind: &str, key: &str) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.join(kind).join(format!("{key}.json")) )
+    }
+}
+
+fn write_entry<T: Serialize>(path: &Path, value: &T) -> anyhow::Result<()> {
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    let part = part_file_path(path);
+    std::fs::write(&part, serde_jsonrc::to_vec(value)?)?;
+    remove_if(path)?;
+    std::fs::rename(part, path)?;
+    Ok(())
+}
+
+/// $XDG_CACHE_HOME/cursinator/api or ~/.cache/cursinator/api
+fn default_api_cache_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|v| !v.is_empty() )
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache") ))?;
+    Some(base.join("cursinator").join("api"))
+}
+
+/// key of per-addon entries, e.g. cf_238222
+pub fn cache_key(id: AddonID) -> String {
+    format!("{}_{}",id.source().prefix(),id.0)
+}
+
+/// key for queries which can't be used as file name directly
+pub fn query_key(prefix: &str, query: &str) -> String {
+    format!("{prefix}_{}",hex::encode(Sha1::digest(query.as_bytes())))
+}
+
+#[test]
+fn test_api_cache() {
+    let dir = std::env::temp_dir().join(format!("cursinator_test_api_cache_{}",std::process::id()));
+    let mut cache = ApiCache {
+        dir: Some(dir.clone()),
+        ttl: Duration::from_secs(3600),
+        refresh: false,
+    };
+
+    assert_eq!(cache.get::<Vec<u32>>("files", "cf_1", false), None);
+    cache.put("files", "cf_1", &vec![1u32,2,3]);
+    assert_eq!(cache.get::<Vec<u32>>("files", "cf_1", false), Some(vec![1,2,3]));
+
+    // refresh bypasses the cache, except in offline mode
+    cache.refresh = true;
+    assert_eq!(cache.get::<Vec<u32>>("files", "cf_1", false), None);
+    assert_eq!(cache.get::<Vec<u32>>("files", "cf_1", true), Some(vec![1,2,3]));
+
+    // stale entries are only used offline
+    cache.refresh = false;
+    cache.ttl = Duration::ZERO;
+    std::thread::sleep(Duration::from_millis(10));
+    assert_eq!(cache.get::<Vec<u32>>("files", "cf_1", false), None);
+    assert_eq!(cache.get::<Vec<u32>>("files", "cf_1", true), Some(vec![1,2,3]));
+
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -1,17 +1,40 @@
+use std::convert::TryInto;
 use std::rc::Rc;
 
 use futures::executor::block_on; //TODO use reqwest::blocking in furse or rewrite to async
 use rustc_hash::FxHashMap;
 
-use crate::addon::AddonID;
+use crate::addon::{AddonID, FileID};
 use crate::addon::files::AddonFile;
 
 use super::*;
+use super::cache::cache_key;
 
 impl API {
     pub fn files(&mut self, id: AddonID) -> FilesResult {
-        if self.offline {hard_error!("Offline mode")};
+        if id.source() == Source::Direct {return FilesResult::NotFound;}
 
+        if let Some(files) = self.cache.get("files", &cache_key(id), self.offline) {
+            return FilesResult::Ok(files);
+        }
+
+        if self.offline {
+            return FilesResult::Error(Rc::new(anyhow::anyhow!("Offline mode, no cached files for {}",id)));
+        }
+
+        let result = match id.source() {
+            Source::Modrinth => self.modrinth_files(id),
+            _ => self.cf_files(id),
+        };
+
+        if let FilesResult::Ok(files) = &result {
+            self.cache.put("files", &cache_key(id), files);
+        }
+
+        result
+    }
+
+    fn cf_files(&mut self, id: AddonID) -> FilesResult {
         dark_log!("API: Query Addon Files for {}",id.0);
 
         match handle_retry(|| self.furse.get_mut().get_mod_files(id.0 as i32), self.retry_count) {
@@ -25,6 +48,42 @@ impl API {
         }
     }
 
+    /// Query specific files of possibly different addons in one request
+    pub fn files_by_id(&mut self, ids: &[FileID]) -> anyhow::Result<Vec<(AddonID,AddonFile)>> {
+        if self.offline {anyhow::bail!("Offline mode, can't query files {}",join_ids(ids))};
+
+        dark_log!("API: Query {} Files",ids.len());
+
+        let ids: Vec<i32> = ids.iter().map(|id| id.0 as i32 ).collect();
+
+        match handle_retry(|| self.furse.get_mut().get_files(ids.clone()), self.retry_count) {
+            Ok(files) => Ok(
+                files.into_iter()
+                    .map(|file| (AddonID(file.mod_id.try_into().unwrap()),file.into()) )
+                    .collect()
+            ),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Look up files by package fingerprint in one request, only exact matches are returned
+    pub fn fingerprint_matches(&mut self, fingerprints: &[u32]) -> anyhow::Result<Vec<(AddonID,AddonFile)>> {
+        if self.offline {anyhow::bail!("Offline mode, can't match fingerprints")};
+
+        dark_log!("API: Match {} Fingerprints",fingerprints.len());
+
+        let fingerprints: Vec<usize> = fingerprints.iter().map(|&f| f as usize ).collect();
+
+        match handle_retry(|| self.furse.get_mut().get_fingerprint_matches(fingerprints.clone()), self.retry_count) {
+            Ok(matches) => Ok(
+                matches.exact_matches.into_iter()
+                    .map(|m| (AddonID(m.id.try_into().unwrap()),m.file.into()) )
+                    .collect()
+            ),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     pub fn files_cached(&mut self, id: AddonID, cache: &mut FxHashMap<AddonID,FilesResult>) -> FilesResult {
         cache.entry(id)
             .or_insert_with(|| self.files(id) )
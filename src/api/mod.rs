@@ -2,18 +2,24 @@ use std::borrow::Cow;
 use std::time::{Duration, SystemTime};
 
 use crate::addon::release_type::ReleaseType;
-use crate::addon::{AddonID, AddonSlug, FileGameVersion, FileID, GameVersion};
+use crate::addon::{AddonID, AddonSlug, FileGameVersion, FileID};
+use crate::addon::loader::Loader;
 use crate::conf::Conf;
-use crate::conf::defaults::{default_api_domain, default_api_headers};
+use crate::addon::source::Source;
 use crate::retrieve_api_key::cf_api_key;
 use crate::{dark_log, hard_error, warn, error};
+use anyhow::bail;
+use cache::{ApiCache, cache_key};
 
 pub mod search;
 pub mod files;
+pub mod modrinth;
+pub mod cache;
 
 use furse::Furse;
 use furse::structures::file_structs::FileIndex;
 use futures::executor::block_on;
+use rustc_hash::FxHashSet;
 use serde_derive::*;
 use ureq::Agent;
 
@@ -23,12 +29,22 @@ pub struct API {
     pub retry_count: u32,
     pub headers: Vec<(String,String)>,
     pub furse: LazyFurse,
+    pub modrinth_domain: String,
     pub offline: bool,
+    pub cache: ApiCache,
 }
 
-impl API {
-    pub fn http_get(&self, url: &str) -> Result<ureq::Response,ureq::Error> {
-        if self.offline {hard_error!("Offline mode")};
+/// HTTP part of the API, which can be shared between download threads
+#[derive(Clone)]
+pub struct HttpClient {
+    pub agent: Agent,
+    pub headers: Vec<(String,String)>,
+    pub offline: bool,
+}
+
+impl HttpClient {
+    pub fn get(&self, url: &str) -> anyhow::Result<ureq::Response> {
+        if self.offline {bail!("Offline mode, can't download {}",url)};
         dark_log!("API: {}",url);
         let mut req = self.agent.get(url);
         for (h,v) in &self.headers {
@@ -39,20 +55,68 @@ impl API {
         Ok(resp)
     }
 
-    #[allow(dead_code)]
-    fn test_api() -> Self {
+    /// request the content starting at offset, the response is 206 if the server supports ranges, else 200 with the full content
+    pub fn get_range(&self, url: &str, offset: u64) -> anyhow::Result<ureq::Response> {
+        if self.offline {bail!("Offline mode, can't download {}",url)};
+        dark_log!("API: {} (from byte {})",url,offset);
+        let mut req = self.agent.get(url);
+        for (h,v) in &self.headers {
+            req = req.set(h,v);
+        };
+        let resp = req.set("Range", &format!("bytes={offset}-")).call()?;
+        assert!(matches!(resp.status(),200 | 206));
+        Ok(resp)
+    }
+}
+
+impl API {
+    pub fn http(&self) -> HttpClient {
+        HttpClient {
+            agent: self.agent.clone(),
+            headers: self.headers.clone(),
+            offline: self.offline,
+        }
+    }
+
+    pub fn http_get(&self, url: &str) -> anyhow::Result<ureq::Response> {
+        self.http().get(url)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn test_api() -> Self {
         Self {
             agent: Agent::new(),
             retry_count: 4,
-            headers: default_api_headers(),
+            headers: crate::conf::defaults::default_api_headers(),
             furse: LazyFurse::new_test(),
+            modrinth_domain: crate::conf::defaults::default_modrinth_api_domain(),
             offline: false,
+            cache: ApiCache::disabled(),
         }
     }
 
     pub fn addon_info(&mut self, id: AddonID) -> anyhow::Result<Option<AddonInfo>> {
-        if self.offline {hard_error!("Offline mode")};
+        if id.source() == Source::Direct {return Ok(None);}
+
+        if let Some(info) = self.cache.get("addon_info", &cache_key(id), self.offline) {
+            return Ok(Some(info));
+        }
+
+        if self.offline {bail!("Offline mode, no cached addon info for {}",id)};
 
+        let info = match id.source() {
+            Source::Modrinth => self.modrinth_addon_info(&id.modrinth_id())?,
+            _ => self.cf_addon_info(id)?,
+        };
+
+        if let Some(info) = &info {
+            self.cache.put("addon_info", &cache_key(id), info);
+        }
+
+        Ok(info)
+    }
+
+    fn cf_addon_info(&mut self, id: AddonID) -> anyhow::Result<Option<AddonInfo>> {
         dark_log!("API: Query Addon Info for {}",id.0);
 
         match handle_retry(|| self.furse.get_mut().get_mod(id.0 as i32), self.retry_count) {
@@ -75,7 +139,62 @@ impl API {
         }
     }
 
+    /// Query info of multiple addons in one request, undistributable addons are omitted
+    pub fn addon_infos(&mut self, ids: &[AddonID]) -> anyhow::Result<Vec<AddonInfo>> {
+        if self.offline {bail!("Offline mode, can't query addon info for {}",join_ids(ids))};
+
+        dark_log!("API: Query Addon Info for {} Addons",ids.len());
+
+        let ids: Vec<i32> = ids.iter().map(|id| id.0 as i32 ).collect();
+
+        match handle_retry(|| self.furse.get_mut().get_mods(ids.clone()), self.retry_count) {
+            Ok(addons) => Ok(
+                addons.into_iter()
+                    .filter(|addon| {
+                        if addon.allow_mod_distribution != Some(true) {
+                            error!("Mod distribution not allowed: {}",addon.slug);
+                        }
+                        addon.allow_mod_distribution == Some(true)
+                    })
+                    .map(|addon| AddonInfo {
+                        id: AddonID(addon.id as u64),
+                        name: addon.name,
+                        slug: AddonSlug(addon.slug),
+                        summary: addon.summary,
+                        latest_files_indexes: addon.latest_files_indexes,
+                    })
+                    .collect()
+            ),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Query which of the addons are flagged as not distributable by the API
+    pub fn undistributable_addons(&mut self, ids: &[AddonID]) -> anyhow::Result<FxHashSet<AddonID>> {
+        if self.offline {bail!("Offline mode, can't query distribution of {}",join_ids(ids))};
+
+        dark_log!("API: Query Distribution for {} Addons",ids.len());
+
+        let ids: Vec<i32> = ids.iter().map(|id| id.0 as i32 ).collect();
+
+        match handle_retry(|| self.furse.get_mut().get_mods(ids.clone()), self.retry_count) {
+            Ok(addons) => Ok(
+                addons.into_iter()
+                    .filter(|addon| addon.allow_mod_distribution != Some(true) )
+                    .map(|addon| AddonID(addon.id as u64) )
+                    .collect()
+            ),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// slug or id may be prefixed with the source, e.g. mr:sodium
     pub fn addon_by_id_or_slug(&mut self, id: &AddonSlug) ->  anyhow::Result<Option<AddonInfo>> {
+        let (source,query) = Source::from_query(id.0.trim());
+        if source == Source::Modrinth {
+            return self.modrinth_addon_info(query);
+        }
+        let id = &AddonSlug(query.to_owned());
         if let Ok(i) = id.0.trim().parse::<u64>() {
             match self.addon_info(AddonID(i)) {
                 Ok(Some(info)) => return Ok(Some(info)),
@@ -113,7 +232,7 @@ fn handle_retry<T>(mut f: impl FnMut() -> Result<T,furse::Error>, retry_count: u
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize,Serialize)]
 #[serde(rename_all="camelCase")]
 pub struct AddonInfo {
     pub id: AddonID,
@@ -124,10 +243,14 @@ pub struct AddonInfo {
 }
 
 impl AddonInfo {
-    pub fn release_type(&self, game_version: &GameVersion) -> Option<ReleaseType> {
+    pub fn release_type(&self, conf: &Conf) -> Option<ReleaseType> {
         let mut max_release_type = None;
         self.latest_files_indexes.iter()
-            .filter(|g| *game_version.0 == g.game_version )
+            .filter(|g| conf.game_version.matches_str(&g.game_version) )
+            .filter(|g| {
+                let loaders: Vec<Loader> = g.mod_loader.iter().filter_map(Loader::from_cf).collect();
+                conf.accepts_loaders(&loaders)
+            })
             .for_each(|g| {
                 let file_release_type = ReleaseType::from(g.release_type.clone());
                 if max_release_type.is_none() || file_release_type >= max_release_type.unwrap() {
@@ -138,6 +261,11 @@ impl AddonInfo {
     }
 }
 
+/// list addons in offline errors
+fn join_ids(ids: &[impl std::fmt::Display]) -> String {
+    ids.iter().map(|id| id.to_string() ).collect::<Vec<_>>().join(", ")
+}
+
 pub(crate) fn parse_retry_duration(retry_after: Option<&str>, fallback: u64) -> u64 {
     if let Some(retry_after) = retry_after {
         if let Ok(wait_until) = httpdate::parse_http_date(retry_after) {
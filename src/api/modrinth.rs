@@ -0,0 +1,344 @@
+use std::rc::Rc;
+
+use anyhow::anyhow;
+use serde::de::DeserializeOwned;
+
+use crate::addon::{AddonID, AddonSlug, FileGameVersion, FileID};
+use crate::addon::dependency::{Dependencies, Dependency};
+use crate::addon::files::{AddonFile, DownloadURL};
+use crate::addon::loader::Loader;
+use crate::addon::release_type::ReleaseType;
+
+use super::*;
+use super::files::FilesResult;
+use super::cache::query_key;
+
+impl API {
+    fn modrinth_get<T: DeserializeOwned>(&self, path: &str) -> Result<Option<T>,anyhow::Error> {
+        if self.offline {bail!("Offline mode, can't query Modrinth {}",path)};
+
+        let url = format!("{}{}",self.modrinth_domain.trim_end_matches('/'),path);
+        dark_log!("API: {}",url);
+
+        let mut retry_i = 0;
+        loop {
+            let resp = self.agent.get(&url)
+                .set("User-Agent", concat!("cursinator/",env!("CARGO_PKG_VERSION")))
+                .call();
+            match resp {
+                Ok(resp) => return Ok(Some(resp.into_json()?)),
+                Err(ureq::Error::Status(404, _)) => return Ok(None),
+                Err(ureq::Error::Status(429, resp)) if retry_i < self.retry_count => {
+                    let wait_duration = parse_retry_duration(
+                        resp.header("Retry-After"),
+                        4u64.pow(retry_i.min(3)),
+                    );
+                    error!("Too many requests, retry in {wait_duration} seconds");
+                    std::thread::sleep(Duration::from_secs(wait_duration));
+                    retry_i += 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    pub fn modrinth_addon_info(&mut self, id_or_slug: &str) -> anyhow::Result<Option<AddonInfo>> {
+        dark_log!("API: Query Modrinth Project {}",id_or_slug);
+
+        let project: Option<MrProject> = self.modrinth_get(&format!("/project/{}",id_or_slug.trim()))?;
+        project.map(TryInto::try_into).transpose()
+    }
+
+    pub fn modrinth_files(&mut self, id: AddonID) -> FilesResult {
+        dark_log!("API: Query Modrinth Versions for {}",id.modrinth_id());
+
+        match self.modrinth_get::<Vec<MrVersion>>(&format!("/project/{}/version",id.modrinth_id())) {
+            Ok(Some(versions)) => {
+                let versions: Result<Vec<AddonFile>,_> = versions.into_iter()
+                    .filter(|v| v.files.iter().any(|f| f.primary ) || v.files.len() == 1 )
+                    .map(TryInto::try_into)
+                    .collect();
+                match versions {
+                    Ok(mut versions) => {
+                        versions.sort_unstable_by(|a,b| (&a.file_date,a.id.0).cmp(&(&b.file_date,b.id.0)) );
+                        FilesResult::Ok(versions)
+                    },
+                    Err(e) => FilesResult::Error(Rc::new(e)),
+                }
+            },
+            Ok(None) => FilesResult::NotFound,
+            Err(e) => FilesResult::Error(Rc::new(e)),
+        }
+    }
+
+    pub fn modrinth_search(&mut self, key: &str, page_size: u64, off: u64) -> anyhow::Result<Vec<AddonInfo>> {
+        anyhow::ensure!(!key.is_empty(), "to-search key cannot be empty");
+
+        let cache_key = query_key("mr", &format!("{key}\n{page_size}\n{off}"));
+        if let Some(result) = self.cache.get("search", &cache_key, self.offline) {
+            return Ok(result);
+        }
+
+        dark_log!("API: Search Modrinth key {key}");
+
+        let facets = r#"[["project_type:mod"]]"#;
+
+        let path = format!(
+            "/search?query={}&facets={}&limit={}&offset={}",
+            url_encode(key), url_encode(facets), page_size, off,
+        );
+
+        let result: MrSearch = self.modrinth_get(&path)?
+            .ok_or_else(|| anyhow!("Search returns 404") )?;
+
+        let result = result.hits.into_iter()
+            .map(|hit| Ok(AddonInfo {
+                id: AddonID::from_modrinth(&hit.project_id).ok_or_else(|| anyhow!("Invalid Modrinth project id: {}",hit.project_id) )?,
+                name: hit.title,
+                slug: AddonSlug(hit.slug),
+                summary: hit.description,
+                latest_files_indexes: vec![],
+            }))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        self.cache.put("search", &cache_key, &result);
+        Ok(result)
+    }
+}
+
+fn url_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(b as char),
+            _ => encoded += &format!("%{:02X}",b),
+        }
+    }
+    encoded
+}
+
+#[derive(Deserialize)]
+struct MrProject {
+    id: String,
+    slug: String,
+    title: String,
+    description: String,
+}
+
+impl TryFrom<MrProject> for AddonInfo {
+    type Error = anyhow::Error;
+
+    fn try_from(project: MrProject) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: AddonID::from_modrinth(&project.id).ok_or_else(|| anyhow!("Invalid Modrinth project id: {}",project.id) )?,
+            name: project.title,
+            slug: AddonSlug(project.slug),
+            summary: project.description,
+            latest_files_indexes: vec![],
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct MrSearch {
+    hits: Vec<MrSearchHit>,
+}
+
+#[derive(Deserialize)]
+struct MrSearchHit {
+    project_id: String,
+    slug: String,
+    title: String,
+    description: String,
+}
+
+#[derive(Deserialize)]
+struct MrVersion {
+    id: String,
+    name: String,
+    date_published: String,
+    version_type: String,
+    game_versions: Vec<String>,
+    loaders: Vec<String>,
+    #[serde(default)]
+    dependencies: Vec<MrDependency>,
+    files: Vec<MrFile>,
+}
+
+#[derive(Deserialize)]
+struct MrDependency {
+    project_id: Option<String>,
+    dependency_type: String,
+}
+
+#[derive(Deserialize)]
+struct MrFile {
+    hashes: MrHashes,
+    url: String,
+    filename: String,
+    primary: bool,
+    size: u64,
+}
+
+#[derive(Deserialize)]
+struct MrHashes {
+    sha1: Option<String>,
+    sha512: Option<String>,
+}
+
+impl TryFrom<MrVersion> for AddonFile {
+    type Error = anyhow::Error;
+
+    fn try_from(version: MrVersion) -> Result<Self, Self::Error> {
+        let id = FileID::from_modrinth(&version.id)
+            .ok_or_else(|| anyhow!("Invalid Modrinth version id: {}",version.id) )?;
+
+        let file_date = chrono::DateTime::parse_from_rfc3339(&version.date_published)?
+            .with_timezone(&chrono::Utc);
+
+        let release_type = match &*version.version_type {
+            "release" => ReleaseType::Release,
+            "beta" => ReleaseType::Beta,
+            "alpha" => ReleaseType::Alpha,
+            v => return Err(anyhow!("Unknown Modrinth version type: {}",v)),
+        };
+
+        let dependencies = version.dependencies.into_iter()
+            .filter_map(|dep| {
+                // dependencies on a specific version only are skipped, they can't be resolved to a project without query
+                let id = AddonID::from_modrinth(dep.project_id.as_deref()?)?;
+                match &*dep.dependency_type {
+                    "required" => Some(Dependency::Required(id)),
+                    "optional" => Some(Dependency::Optional(id)),
+                    "incompatible" => Some(Dependency::Incompatible(id)),
+                    "embedded" => Some(Dependency::EmbeddedLibrary(id)),
+                    _ => None,
+                }
+            })
+            .collect::<Vec<Dependency>>();
+
+        let mut files = version.files;
+        let file = match files.iter().position(|f| f.primary ) {
+            Some(i) => files.swap_remove(i),
+            None => files.swap_remove(0),
+        };
+
+        let loaders = version.loaders.iter()
+            .filter_map(|l| Loader::from_name(l) )
+            .collect();
+
+        // loaders are listed in game versions like on CurseForge
+        let game_version = version.game_versions.into_iter()
+            .chain(version.loaders.iter().map(|l| capitalize(l) ))
+            .map(FileGameVersion)
+            .collect();
+
+        Ok(Self {
+            id,
+            display_name: version.name,
+            file_name: file.filename,
+            file_date: format!("{}Z",file_date.to_rfc3339().split('+').next().unwrap()),
+            file_length: file.size,
+            release_type,
+            download_url: Some(DownloadURL(file.url)),
+            is_alternate: false,
+            alternate_file_id: 0,
+            dependencies: Dependencies::from(dependencies),
+            is_available: true,
+            package_fingerprint: 0,
+            game_version,
+            loaders,
+            has_install_script: false,
+            sha1_hash: file.hashes.sha1,
+            sha256_hash: None,
+            sha512_hash: file.hashes.sha512,
+        })
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut c = s.chars();
+    match c.next() {
+        Some(first) => first.to_uppercase().chain(c).collect(),
+        None => String::new(),
+    }
+}
+
+/// serve canned responses for the given paths on a local port, returns the base url
+#[cfg(test)]
+pub(crate) fn mock_server(routes: Vec<(impl Into<String>,String)>) -> String {
+    use std::io::{BufRead, BufReader, Write};
+
+    let routes: Vec<(String,String)> = routes.into_iter().map(|(p,b)| (p.into(),b) ).collect();
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(s) => s,
+                Err(_) => break,
+            };
+            let mut request_line = String::new();
+            BufReader::new(&stream).read_line(&mut request_line).unwrap();
+            let path = request_line.split(' ').nth(1).unwrap_or("");
+            let path = path.split('?').next().unwrap();
+
+            let response = match routes.iter().find(|(p,_)| *p == path ) {
+                Some((_,body)) => format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",body.len(),body),
+                None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_owned(),
+            };
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+
+    format!("http://{addr}")
+}
+
+#[test]
+fn test_modrinth_mock() {
+    let project = r#"{"id":"AANobbMI","slug":"sodium","title":"Sodium","description":"Rendering engine","project_type":"mod"}"#;
+    let versions = r#"[
+        {"id":"yaoBL9D9","project_id":"AANobbMI","name":"Sodium 0.4.10","version_number":"mc1.19.2-0.4.4","date_published":"2022-10-04T18:00:00.000000Z",
+         "version_type":"release","game_versions":["1.19.2"],"loaders":["fabric"],
+         "dependencies":[{"version_id":null,"project_id":"P7dR8mSH","file_name":null,"dependency_type":"required"},
+                         {"version_id":"abcdefgh","project_id":null,"file_name":null,"dependency_type":"optional"}],
+         "files":[{"hashes":{"sha1":"aa","sha512":"bb"},"url":"https://cdn.modrinth.com/sodium-0.4.4.jar","filename":"sodium-0.4.4.jar","primary":true,"size":10}]},
+        {"id":"b4hTi3mo","project_id":"AANobbMI","name":"Sodium 0.4.2","version_number":"mc1.19.2-0.4.2","date_published":"2022-08-01T10:00:00Z",
+         "version_type":"beta","game_versions":["1.19.2"],"loaders":["fabric","quilt"],"dependencies":[],
+         "files":[{"hashes":{"sha1":"cc","sha512":"dd"},"url":"https://cdn.modrinth.com/sodium-0.4.2.jar","filename":"sodium-0.4.2.jar","primary":false,"size":20}]}
+    ]"#;
+    let base = mock_server(vec![
+        ("/project/sodium", project.to_owned()),
+        ("/project/AANobbMI/version", versions.to_owned()),
+        ("/search", r#"{"hits":[{"project_id":"AANobbMI","slug":"sodium","title":"Sodium","description":"Rendering engine"}]}"#.to_owned()),
+    ]);
+
+    let mut api = API::test_api();
+    api.modrinth_domain = base;
+
+    let info = api.modrinth_addon_info("sodium").unwrap().unwrap();
+    assert!(info.id == AddonID::from_modrinth("AANobbMI").unwrap());
+    assert_eq!(info.id.source(), Source::Modrinth);
+
+    assert!(api.modrinth_addon_info("missing").unwrap().is_none());
+
+    let files = match api.files(info.id) {
+        FilesResult::Ok(v) => v,
+        FilesResult::NotFound => panic!("no results"),
+        FilesResult::Error(e) => panic!("{}",e),
+    };
+    // ordered by publish date
+    assert_eq!(files[0].file_name, "sodium-0.4.2.jar");
+    assert_eq!(files[1].file_name, "sodium-0.4.4.jar");
+    assert!(files[0].older_than(&files[1]));
+    assert_eq!(files[0].release_type, ReleaseType::Beta);
+    assert_eq!(files[1].sha512_hash.as_deref(), Some("bb"));
+    assert!(files[1].dependencies.iter_required().eq([AddonID::from_modrinth("P7dR8mSH").unwrap()]));
+    assert_eq!(files[1].dependencies.len(), 1);
+    assert!(files[0].game_version.iter().any(|v| v.0 == "Quilt" ));
+
+    let found = api.modrinth_search("sodium", 10, 0).unwrap();
+    assert_eq!(found[0].slug.0, "sodium");
+}
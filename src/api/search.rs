@@ -5,24 +5,33 @@ use furse::structures::search_query::SearchQuery;
 use crate::addon::AddonSlug;
 
 use super::*;
+use super::cache::query_key;
 
 impl API {
     pub fn search_key(&mut self, key: &str, page_size: u64, off: u64) -> anyhow::Result<Vec<AddonInfo>> {
         anyhow::ensure!(!key.is_empty(), "to-search key cannot be empty");
 
+        let cache_key = query_key("cf", &format!("{key}\n{page_size}\n{off}"));
+        if let Some(result) = self.cache.get("search", &cache_key, self.offline) {
+            return Ok(result);
+        }
+
         dark_log!("API: Search key {key}");
         
-        self.search_query(&SearchQuery {
+        let result = self.search_query(&SearchQuery {
             class_id: Some(6),
             search_filter: Some(key),
             page_size: Some(page_size as usize),
             index: off as usize,
             ..Default::default()
-        })
+        })?;
+
+        self.cache.put("search", &cache_key, &result);
+        Ok(result)
     }
 
     pub fn search_query(&mut self, query: &SearchQuery) -> anyhow::Result<Vec<AddonInfo>> {
-        if self.offline {hard_error!("Offline mode")};
+        if self.offline {anyhow::bail!("Offline mode, search results are not cached")};
 
         match handle_retry(|| self.furse.get_mut().search_mods(query), self.retry_count) {
             Ok(mod_files) => {Ok(
@@ -0,0 +1,184 @@
+use std::io::Read;
+use std::path::Path;
+
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+
+use crate::addon::{AddonID, AddonSlug, FileID};
+use crate::addon::dependency::{Dependencies, Dependency};
+use crate::addon::files::{AddonFile, DownloadURL};
+use crate::addon::fingerprint::fingerprint;
+use crate::addon::local::{LocalAddon, UpdateOpt};
+use crate::addon::release_type::ReleaseType;
+use crate::addon::rtm::ReleaseTypeMode;
+use crate::addon::source::Source;
+use crate::api::API;
+use crate::conf::Repo;
+use crate::util::fs::{Finalize, create_guarded_cache_link, relative_path, remove_if};
+use crate::{Op, hard_assert, hard_error, log_error, unwrap_result_error};
+
+pub fn main(
+    o: &Op,
+    api: &mut API,
+    repo: &mut Repo,
+    slug: Option<String>,
+    sha1: Option<String>,
+    sha256: Option<String>,
+    source: String,
+) -> bool {
+    let source = source.trim();
+    let is_url = source.starts_with("http://") || source.starts_with("https://");
+
+    // vendored files are referenced as file://path
+    let (download_url,file_name) = if is_url {
+        let file_name = source.split(['?','#']).next().unwrap()
+            .rsplit('/').next().unwrap_or("")
+            .to_owned();
+        (source.to_owned(),file_name)
+    } else {
+        let path = Path::new(source.strip_prefix("file://").unwrap_or(source));
+        hard_assert!(path.is_file(),"File not found: {}",path.to_string_lossy());
+        let file_name = path.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned() );
+        // stored relative to the repo json, so it works from any directory and in other checkouts
+        let relative = match unwrap_result_error!(relative_path(path, &repo.conf.repo_dir), |e|"Failed to resolve path: {}",e) {
+            Some(v) => v,
+            None => hard_error!("File {} can't be referenced relative to the repo json",path.to_string_lossy()),
+        };
+        (format!("file://{}",relative.to_string_lossy()),file_name)
+    };
+    hard_assert!(!file_name.is_empty(),"Failed to derive file name from {}",source);
+
+    let slug = AddonSlug(slug.unwrap_or_else(|| {
+        file_name.rsplit_once('.').map_or(&*file_name, |(stem,_)| stem ).to_owned()
+    }));
+
+    // an existing direct addon with the slug is replaced
+    let previous = repo.addons.values().find(|a| a.slug == slug );
+    if let Some(previous) = previous {
+        hard_assert!(previous.id.source() == Source::Direct,"Addon with slug {} already exists",slug);
+    }
+    if let Some(other) = repo.addons.values().find(|a| a.installed.as_ref().map_or(false, |f| f.file_name == file_name ) && a.slug != slug ) {
+        hard_error!("File {} is already installed by {}",file_name,other.slug);
+    }
+
+    let buf = {
+        let mut buf = Vec::with_capacity(1024*1024);
+        if let Some(local_path) = DownloadURL(download_url.clone()).local_path_in(&repo.conf.repo_dir) {
+            unwrap_result_error!(std::fs::File::open(local_path).and_then(|mut f| f.read_to_end(&mut buf) ), |e|"Failed to read file: {}",e);
+        } else {
+            let resp = unwrap_result_error!(api.http_get(&download_url), |e|"Failed to download: {}",e);
+            unwrap_result_error!(resp.into_reader().read_to_end(&mut buf), |e|"Failed to download: {}",e);
+        }
+        buf
+    };
+
+    let sha1_str = hex::encode(Sha1::digest(&buf));
+    let sha256_str = hex::encode(Sha256::digest(&buf));
+
+    if let Some(sha1) = &sha1 {
+        hard_assert!(sha1.trim().eq_ignore_ascii_case(&sha1_str),"SHA-1 mismatch, file has {}",sha1_str);
+    }
+    if let Some(sha256) = &sha256 {
+        hard_assert!(sha256.trim().eq_ignore_ascii_case(&sha256_str),"SHA-256 mismatch, file has {}",sha256_str);
+    }
+
+    // pin the given hash, or the SHA-1 of the file if none given
+    let (sha1_hash,sha256_hash) = match (sha1,sha256) {
+        (None,Some(_)) => (None,Some(sha256_str)),
+        (Some(_),Some(_)) => (Some(sha1_str),Some(sha256_str)),
+        _ => (Some(sha1_str),None),
+    };
+
+    let file_id = FileID::direct(&hex::decode(sha1_hash.as_ref().or(sha256_hash.as_ref()).unwrap()).unwrap());
+
+    let now = chrono::Utc::now();
+
+    let file = AddonFile {
+        id: file_id,
+        display_name: file_name.clone(),
+        file_name: file_name.clone(),
+        file_date: format!("{}Z",now.to_rfc3339().split('+').next().unwrap()),
+        file_length: buf.len() as u64,
+        release_type: ReleaseType::Release,
+        download_url: Some(DownloadURL(download_url)),
+        is_alternate: false,
+        alternate_file_id: 0,
+        dependencies: Dependencies::from(Vec::<Dependency>::new()),
+        is_available: true,
+        package_fingerprint: fingerprint(&buf),
+        game_version: vec![],
+        loaders: vec![],
+        has_install_script: false,
+        sha1_hash,
+        sha256_hash,
+        sha512_hash: None,
+    };
+
+    let addon_id = previous.map_or_else(|| next_direct_id(repo), |a| a.id );
+    let previous_file = previous.and_then(|a| a.installed.clone() );
+    let disabled = previous.map_or(false, |a| a.disabled );
+
+    let name = previous.map_or_else(|| file_name.clone(), |a| a.name.clone() );
+
+    let addon = LocalAddon {
+        id: addon_id,
+        slug,
+        name,
+        channel: ReleaseTypeMode::new(false,false,false),
+        update_opt: UpdateOpt::Explicit,
+        manually_installed: true,
+        version_blacklist: None,
+        positive_negative_in_filename: true,
+        installed: Some(file),
+        disabled,
+        declined_optionals: vec![],
+        version_constraint: None,
+    };
+    let (slug,file) = (&addon.slug,addon.installed.as_ref().unwrap());
+
+    o.plan().install(&addon, &repo.addons);
+    eprintln!("Add: {} ({}){}",slug,file_name,o.suffix());
+
+    if o.noop {
+        return false;
+    }
+
+    let paths = file.file_paths_new(addon_id, disabled, &repo.conf);
+
+    let result = (|| -> anyhow::Result<()> {
+        repo.conf.ensure_cache_dir()?;
+
+        let download_to = paths.cache_path.as_ref().unwrap_or(&paths.part_path);
+        std::fs::write(download_to, &buf)?;
+
+        let mut finalizer = match &paths.cache_path {
+            Some(cache_path) => create_guarded_cache_link(cache_path.clone(), paths.path.clone(), repo.conf.cache_link_mode)?,
+            None => Finalize::for_part_path(paths.path.clone(), paths.part_path.clone(), false),
+        };
+        if repo.conf.url_txt {
+            finalizer = finalizer + file.write_url_txt(&paths, &repo.conf, api, &hex::encode(Sha1::digest(&buf)))?;
+        }
+        finalizer.finalize()
+    })();
+    unwrap_result_error!(result, |e|"Failed to add {}: {}",slug,e);
+
+    if let Some(previous_file) = previous_file.filter(|f| f.file_name != file.file_name ) {
+        let previous_paths = previous_file.file_paths_current(addon_id, false, &repo.conf);
+        eprintln!("Remove previous version: {}",previous_paths.path.to_string_lossy());
+        log_error!(remove_if(&previous_paths.path));
+        log_error!(remove_if(&previous_paths.url_txt_path));
+    }
+
+    repo.addons.insert(addon_id, addon);
+
+    true
+}
+
+fn next_direct_id(repo: &Repo) -> AddonID {
+    let n = repo.addons.keys()
+        .filter(|id| id.source() == Source::Direct )
+        .map(|id| id.direct_n() + 1 )
+        .max()
+        .unwrap_or(0);
+    AddonID::direct(n)
+}
@@ -0,0 +1,132 @@
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::addon::AddonID;
+use crate::addon::fingerprint::fingerprint_file;
+use crate::addon::local::{LocalAddon, UpdateOpt};
+use crate::addon::rtm::ReleaseTypeMode;
+use crate::api::API;
+use crate::conf::Repo;
+use crate::{Op, error, unwrap_result_error, warn};
+
+pub fn main(
+    o: &Op,
+    api: &mut API,
+    repo: &mut Repo,
+) -> bool {
+    let known: FxHashSet<&str> = repo.addons.values()
+        .filter_map(|a| a.installed.as_ref() )
+        .map(|f| f.file_name.as_str() )
+        .collect();
+
+    // (file name, disabled) of jars not installed by cursinator
+    let mut unknown: Vec<(String,bool)> = vec![];
+
+    let dir = unwrap_result_error!(std::fs::read_dir("."), |e|"Failed to read addon directory: {}",e);
+
+    for entry in dir.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let (file_name,disabled) = match name.strip_suffix(".disabled") {
+            Some(n) => (n.to_owned(),true),
+            None => (name.clone(),false),
+        };
+        if !file_name.ends_with(".jar") || known.contains(file_name.as_str()) || !entry.path().is_file() {
+            continue;
+        }
+        unknown.push((file_name,disabled));
+    }
+    unknown.sort_unstable();
+
+    if unknown.is_empty() {
+        eprintln!("No unknown addon files");
+        return false;
+    }
+
+    let mut fingerprints: FxHashMap<u32,(String,bool)> = FxHashMap::default();
+    let mut unmatched: Vec<String> = vec![];
+
+    for (file_name,disabled) in unknown {
+        let path = if disabled {format!("{file_name}.disabled")} else {file_name.clone()};
+        match fingerprint_file(&path) {
+            Ok(f) => {fingerprints.insert(f, (file_name,disabled));},
+            Err(e) => {
+                error!("Failed to read {}: {}",path,e);
+                unmatched.push(path);
+            },
+        }
+    }
+
+    let query: Vec<u32> = fingerprints.keys().copied().collect();
+    let matches = unwrap_result_error!(api.fingerprint_matches(&query), |e|"Failed to match fingerprints: {}",e);
+
+    let mut matched = vec![];
+
+    for (addon_id,file) in matches {
+        if let Some((file_name,disabled)) = fingerprints.remove(&file.package_fingerprint) {
+            if let Some(existing) = repo.addons.get(&addon_id).filter(|a| a.installed.is_some() ) {
+                warn!("{} is already installed ({}), skipping {}",existing.slug,existing.installed.as_ref().unwrap().file_name,file_name);
+                unmatched.push(file_name);
+                continue;
+            }
+            if file.file_name != file_name {
+                warn!("File name of {} differs from API ({}), skipping",file_name,file.file_name);
+                unmatched.push(file_name);
+                continue;
+            }
+            matched.push((addon_id,file,disabled));
+        }
+    }
+
+    unmatched.extend(fingerprints.into_values().map(|(file_name,disabled)| {
+        if disabled {format!("{file_name}.disabled")} else {file_name}
+    }));
+
+    let mut modified = false;
+
+    if !matched.is_empty() {
+        let ids: Vec<AddonID> = matched.iter().map(|(id,_,_)| *id ).collect();
+        let infos = unwrap_result_error!(api.addon_infos(&ids), |e|"Failed to fetch addon info: {}",e);
+
+        // addons required by other adopted or installed addons are considered auto-installed
+        let dependencies: FxHashSet<AddonID> = matched.iter()
+            .map(|(_,f,_)| f )
+            .chain(repo.addons.values().filter_map(|a| a.installed.as_ref() ))
+            .flat_map(|f| f.dependencies.iter_required() )
+            .collect();
+
+        for (addon_id,file,disabled) in matched {
+            let info = match infos.iter().find(|i| i.id == addon_id ) {
+                Some(i) => i,
+                None => {unmatched.push(file.file_name);continue},
+            };
+
+            let addon = LocalAddon {
+                id: addon_id,
+                slug: info.slug.clone(),
+                name: info.name.clone(),
+                channel: ReleaseTypeMode::new(false,false,false),
+                update_opt: UpdateOpt::All,
+                manually_installed: !dependencies.contains(&addon_id),
+                version_blacklist: None,
+                positive_negative_in_filename: true,
+                installed: Some(file),
+                disabled,
+                declined_optionals: vec![],
+                version_constraint: None,
+            };
+
+            o.plan().install(&addon, &repo.addons);
+            eprintln!("Adopt: {} ({}){}",addon.slug,addon.installed.as_ref().unwrap().file_name,o.suffix());
+
+            repo.addons.insert(addon_id, addon);
+            modified = true;
+        }
+    }
+
+    unmatched.sort_unstable();
+
+    for file_name in &unmatched {
+        eprintln!("Unmatched: {}",file_name);
+    }
+
+    modified && !o.noop
+}
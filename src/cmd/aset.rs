@@ -1,6 +1,7 @@
 use std::fmt::Display;
 
-use crate::addon::local::UpdateOpt;
+use crate::addon::local::{LocalAddon, UpdateOpt};
+use crate::addon::version_constraint::VersionConstraint;
 use crate::conf::Repo;
 use crate::util::match_str::find_installed_mod_by_key;
 use crate::print::error::unwrap_match;
@@ -49,13 +50,39 @@ pub fn main(
                 eprintln!("\tversion-blacklist={}",addon.version_blacklist.as_ref().map(|s| s as &str).unwrap_or(""));
                 false
             },
+            WhatASet::VersionConstraint => if let Some(value) = value {
+                let constraint = if value == "-" {
+                    None
+                } else {
+                    Some(unwrap_result_error!(value.parse::<VersionConstraint>(), |e|"Invalid version constraint: {}",e))
+                };
+                if o.noop {return false;}
+                addon.version_constraint = constraint;
+                true
+            } else {
+                eprintln!("\tversion-constraint={}",version_constraint(addon));
+                false
+            },
+            WhatASet::DeclinedOptionals => if let Some(value) = value {
+                if value != "-" {
+                    hard_error!("declined-optionals can only be cleared with -");
+                }
+                if o.noop {return false;}
+                addon.declined_optionals.clear();
+                true
+            } else {
+                eprintln!("\tdeclined-optionals={}",declined_optionals(addon));
+                false
+            },
         }
     }else{
         eprintln!(
-            "\tupdate-opt={}\n\tmanually-installed={}\n\tversion-blacklist={}\n\nNot all options exposed yet, refer repo.json",
+            "\tupdate-opt={}\n\tmanually-installed={}\n\tversion-blacklist={}\n\tversion-constraint={}\n\tdeclined-optionals={}\n\nNot all options exposed yet, refer repo.json",
             addon.update_opt,
             addon.manually_installed,
             addon.version_blacklist.as_ref().map(|s| s as &str).unwrap_or(""),
+            version_constraint(addon),
+            declined_optionals(addon),
         );
         false
     }
@@ -66,6 +93,8 @@ fn match_key(s: &str) -> WhatASet {
         (WhatASet::UpdateOpt,"update-opt"),
         (WhatASet::ManuallyInstalled,"manually-installed"),
         (WhatASet::VersionBlacklist,"version-blacklist"),
+        (WhatASet::VersionConstraint,"version-constraint"),
+        (WhatASet::DeclinedOptionals,"declined-optionals"),
     ][..]];
     match match_str(s,to_match) {
         Ok(r) => r.z,
@@ -85,6 +114,19 @@ enum WhatASet {
     UpdateOpt,
     ManuallyInstalled,
     VersionBlacklist,
+    VersionConstraint,
+    DeclinedOptionals,
+}
+
+fn version_constraint(addon: &LocalAddon) -> String {
+    addon.version_constraint.as_ref().map_or(String::new(), |c| c.to_string() )
+}
+
+fn declined_optionals(addon: &LocalAddon) -> String {
+    addon.declined_optionals.iter()
+        .map(|id| id.to_string() )
+        .collect::<Vec<_>>()
+        .join(",")
 }
 
 fn match_updateopt(s: &str) -> UpdateOpt {
@@ -14,6 +14,7 @@ pub fn main(
         let mut repeat = false;
 
         for id in autoremovable(&repo.addons) {
+            o.plan().remove(repo.addons.get(&id).unwrap(), purge);
             eprintln!("Autoremove: {}{}",repo.addons.get(&id).unwrap().slug,o.suffix());
             if !o.noop {
                 {
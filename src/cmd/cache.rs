@@ -0,0 +1,209 @@
+use std::path::{Path, PathBuf};
+
+use rustc_hash::FxHashMap;
+
+use crate::addon::AddonID;
+use crate::conf::Repo;
+use crate::conf::cache_index::{CacheIndex, is_index_file};
+use crate::util::fs::remove_if;
+use crate::{CacheCmd, Op, dark_log, error, hard_error, unwrap_result_error, warn};
+
+pub fn main(
+    o: &Op,
+    repo: &Repo,
+    cmd: CacheCmd,
+) -> bool {
+    let cache_dir = match &repo.conf.symlink_cache_path {
+        Some(v) => v,
+        None => hard_error!("No symlink_cache_path set in repo"),
+    };
+    unwrap_result_error!(repo.conf.ensure_cache_dir(), |e|"Failed to access cache: {}",e);
+
+    match cmd {
+        CacheCmd::Gc { force } => gc(o, repo, cache_dir, force),
+        CacheCmd::Verify {} => verify(o, cache_dir),
+        CacheCmd::Stats {} => stats(cache_dir),
+    }
+
+    false
+}
+
+fn gc(o: &Op, repo: &Repo, cache_dir: &Path, force: bool) {
+    let this_repo = unwrap_result_error!(std::fs::canonicalize(&o.conf), |e|"Failed to resolve repo path: {}",e);
+    let cache_dir_canon = unwrap_result_error!(std::fs::canonicalize(cache_dir), |e|"Failed to resolve cache path: {}",e);
+
+    let run = |index: &mut CacheIndex| -> anyhow::Result<(usize,u64,usize)> {
+        index.register(this_repo.clone(), repo);
+
+        // refresh the references of the other repos, repos which are gone or use another cache are dropped
+        let repo_paths: Vec<PathBuf> = index.repos.keys().filter(|p| **p != this_repo ).cloned().collect();
+        for repo_path in repo_paths {
+            match Repo::load(&repo_path) {
+                Ok(Some(other)) if uses_cache_dir(&repo_path, &other, &cache_dir_canon) => index.register(repo_path, &other),
+                Ok(_) => {
+                    dark_log!("Repo no longer uses the cache: {}",repo_path.to_string_lossy());
+                    index.unregister(&repo_path);
+                },
+                Err(e) => error!("Failed to read repo {}, keeping its cache files: {}",repo_path.to_string_lossy(),e),
+            }
+        }
+
+        let mut removed = 0;
+        let mut freed = 0;
+        let mut kept = 0;
+
+        for (name,path) in cache_files(cache_dir)? {
+            // leftover .part of unreferenced files are also removed
+            let name = cache_name(&name);
+            if index.is_referenced(name) {continue;}
+            // files never indexed may belong to repos which weren't registered yet
+            if !force && !index.entries.contains_key(name) {
+                dark_log!("Not in cache index, keep: {}",path.to_string_lossy());
+                kept += 1;
+                continue;
+            }
+            eprintln!("Remove: {}{}",path.to_string_lossy(),o.suffix());
+            freed += path.metadata().map_or(0, |m| m.len() );
+            removed += 1;
+            if !o.noop {
+                remove_if(&path)?;
+            }
+        }
+
+        index.retain_referenced();
+
+        Ok((removed,freed,kept))
+    };
+
+    let result = if o.noop {
+        CacheIndex::read_locked(cache_dir, run)
+    } else {
+        CacheIndex::with_locked(cache_dir, run)
+    };
+
+    let (removed,freed,kept) = unwrap_result_error!(result, |e|"Failed to collect cache garbage: {}",e);
+
+    eprintln!("Removed {} files, {}{}",removed,format_size(freed),o.suffix());
+    if kept != 0 {
+        warn!("Kept {} files not in the cache index, run e.g. download-all in every repo using the cache or remove them with cache gc --force",kept);
+    }
+}
+
+fn verify(o: &Op, cache_dir: &Path) {
+    let result = CacheIndex::read_locked(cache_dir, |index| {
+        let mut valid = 0;
+        let mut invalid = 0;
+
+        for (name,path) in cache_files(cache_dir)? {
+            if name.ends_with(".part") {continue;}
+            let entry = match index.entries.get(&name) {
+                Some(v) => v,
+                None => {warn!("Not in cache index: {}",name);continue},
+            };
+            match entry.file.is_downloaded_addon_valid(&path) {
+                Ok(Some(_)) => valid += 1,
+                Ok(None) => {
+                    error!("Invalid cache file, remove: {}{}",name,o.suffix());
+                    invalid += 1;
+                    if !o.noop {
+                        remove_if(&path)?;
+                    }
+                },
+                Err(e) => {
+                    error!("Failed to verify {}: {}",name,e);
+                    invalid += 1;
+                },
+            }
+        }
+
+        Ok((valid,invalid))
+    });
+
+    let (valid,invalid) = unwrap_result_error!(result, |e|"Failed to verify cache: {}",e);
+
+    eprintln!("Verified {} files, {} invalid",valid+invalid,invalid);
+}
+
+fn stats(cache_dir: &Path) {
+    let result = CacheIndex::read_locked(cache_dir, |index| {
+        let mut per_addon: FxHashMap<Option<AddonID>,(String,usize,u64)> = FxHashMap::default();
+        let mut orphaned = (0,0);
+
+        for (name,path) in cache_files(cache_dir)? {
+            let len = path.metadata().map_or(0, |m| m.len() );
+            let entry = index.entries.get(cache_name(&name));
+            let stat = per_addon.entry(entry.map(|e| e.id ))
+                .or_insert_with(|| (entry.map_or("(not indexed)".to_owned(), |e| e.slug.0.clone() ),0,0) );
+            stat.1 += 1;
+            stat.2 += len;
+            if !index.is_referenced(cache_name(&name)) {
+                orphaned.0 += 1;
+                orphaned.1 += len;
+            }
+        }
+
+        Ok((per_addon,orphaned))
+    });
+
+    let (per_addon,orphaned) = unwrap_result_error!(result, |e|"Failed to read cache: {}",e);
+
+    let mut per_addon: Vec<_> = per_addon.into_values().collect();
+    per_addon.sort_unstable_by(|a,b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0) ) );
+
+    for (slug,files,bytes) in &per_addon {
+        println!("{:>10} {:>4} {}",format_size(*bytes),files,slug);
+    }
+
+    let files: usize = per_addon.iter().map(|s| s.1 ).sum();
+    let bytes: u64 = per_addon.iter().map(|s| s.2 ).sum();
+    eprintln!("Total: {} in {} files, {} in {} files not referenced by any repo",format_size(bytes),files,format_size(orphaned.1),orphaned.0);
+}
+
+/// files in the cache dir by name, sorted
+fn cache_files(cache_dir: &Path) -> anyhow::Result<Vec<(String,PathBuf)>> {
+    let mut files = vec![];
+    for entry in std::fs::read_dir(cache_dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {continue;}
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if is_index_file(&name) {continue;}
+        files.push((name,entry.path()));
+    }
+    files.sort_unstable();
+    Ok(files)
+}
+
+/// .part files count as the file they are downloading
+fn cache_name(name: &str) -> &str {
+    name.strip_suffix(".part").unwrap_or(name)
+}
+
+/// the cache path of other repos is relative to their directory
+fn uses_cache_dir(repo_path: &Path, repo: &Repo, cache_dir: &Path) -> bool {
+    let base = repo_path.parent().unwrap_or(Path::new("."));
+    repo.conf.symlink_cache_path.as_ref()
+        .and_then(|p| std::fs::canonicalize(base.join(p)).ok() )
+        .map_or(false, |p| p == cache_dir )
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str;4] = ["KiB","MiB","GiB","TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.;
+    let mut unit = 0;
+    while size >= 1024. && unit < UNITS.len()-1 {
+        size /= 1024.;
+        unit += 1;
+    }
+    format!("{size:.1} {}",UNITS[unit])
+}
+
+#[test]
+fn test_format_size() {
+    assert_eq!(format_size(0), "0 B");
+    assert_eq!(format_size(1023), "1023 B");
+    assert_eq!(format_size(1024), "1.0 KiB");
+    assert_eq!(format_size(5*1024*1024+512*1024), "5.5 MiB");
+}
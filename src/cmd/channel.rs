@@ -1,7 +1,8 @@
-use crate::{Op, unwrap_result_error};
+use crate::{Op, OutputFormat, unwrap_result_error};
 use crate::conf::Repo;
 use crate::op::channel::decode_channel;
 use crate::print::error::unwrap_match;
+use crate::print::records::{AddonRecord, print_records};
 use crate::util::match_str::find_installed_mod_by_key;
 
 pub fn main(
@@ -16,12 +17,21 @@ pub fn main(
         let new_channel = decode_channel(&value);
         let addon = repo.addons.get_mut(&addon_id).unwrap();
         eprintln!("{}: {} -> {}{}",addon.slug,addon.channel,new_channel,o.suffix());
+        if o.format != OutputFormat::Text {
+            let mut record = AddonRecord::local(addon);
+            record.channel = Some(new_channel.to_string());
+            print_records(&[record], o.format);
+        }
         if !o.noop && addon.channel != new_channel {
             addon.channel = new_channel;
             return true;
         }
     }else{
         let addon = repo.addons.get(&addon_id).unwrap();
+        if o.format != OutputFormat::Text {
+            print_records(&[AddonRecord::local(addon)], o.format);
+            return false;
+        }
         eprintln!("{}: {}{}",addon.slug,addon.channel,o.suffix());
     }
     
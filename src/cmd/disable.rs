@@ -1,10 +1,94 @@
+use rustc_hash::FxHashSet;
+
 use crate::Op;
+use crate::addon::AddonID;
 use crate::conf::Repo;
+use crate::op::remove::has_dependents;
+use crate::print::error::unwrap_match;
+use crate::util::match_str::find_installed_mod_by_key;
+use crate::{error,warn,unwrap_result_error};
 
 pub fn main(
     o: &Op,
     repo: &mut Repo,
+    force: bool,
+    disable_depending: bool,
     addon: String,
 ) -> bool {
-    todo!()
+    let addon_id = unwrap_result_error!(unwrap_match(find_installed_mod_by_key(&addon,&repo.addons,false))).z;
+
+    let mut to_disable = vec![addon_id];
+
+    if disable_depending {
+        // collect enabled dependents recursively
+        let mut seen = FxHashSet::default();
+        seen.insert(addon_id);
+        let mut i = 0;
+        while i < to_disable.len() {
+            for d in has_dependents(to_disable[i], &repo.addons) {
+                if !d.disabled && seen.insert(d.id) {
+                    to_disable.push(d.id);
+                }
+            }
+            i += 1;
+        }
+    } else {
+        let dependents: Vec<_> = has_dependents(addon_id, &repo.addons)
+            .into_iter()
+            .filter(|d| !d.disabled )
+            .collect();
+
+        let slug = &repo.addons.get(&addon_id).unwrap().slug;
+
+        if !dependents.is_empty() {
+            if !force {
+                error!("Addon has dependents: {}{}",slug,o.suffix());
+            } else {
+                warn!("Disabling Addon with dependents: {}{}",slug,o.suffix());
+            }
+
+            for d in dependents {
+                eprint!(" {}",d.slug);
+            }
+            eprintln!("{}",o.suffix());
+
+            if !force {
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let mut modified = false;
+
+    for id in to_disable {
+        modified |= disable(o, repo, id);
+    }
+
+    modified
+}
+
+fn disable(o: &Op, repo: &mut Repo, addon_id: AddonID) -> bool {
+    let addon = repo.addons.get_mut(&addon_id).unwrap();
+    let installed = addon.installed.as_ref().unwrap();
+
+    let paths = installed.file_paths_current(addon_id, !o.noop, &repo.conf);
+
+    if paths.disabled && addon.disabled {
+        eprintln!("Already disabled: {}{}",addon.slug,o.suffix());
+        return false;
+    }
+
+    eprintln!("Disable: {}{}",addon.slug,o.suffix());
+
+    if !o.noop {
+        let new_paths = installed.file_paths_new(addon_id, true, &repo.conf);
+        unwrap_result_error!(
+            paths.rename_to(&new_paths),
+            |e|"Failed to disable addon: {}",e
+        );
+        addon.disabled = true;
+        return true;
+    }
+
+    false
 }
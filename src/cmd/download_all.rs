@@ -1,8 +1,9 @@
-use crate::util::fs::Finalize;
+use crate::util::fs::{Finalize, is_existing};
+use crate::util::pool::par_map;
 use crate::Op;
 use crate::api::API;
 use crate::conf::Repo;
-use crate::error;
+use crate::{error, unwrap_result_error};
 
 pub fn main(
     o: &Op,
@@ -12,18 +13,43 @@ pub fn main(
 ) -> bool {
     let mut finalizers: Vec<Finalize> = vec![];
 
+    let mut jobs = vec![];
+
     for (&addon_id,addon) in repo.addons.iter() {
         if let Some(addon_file) = addon.installed.as_ref() {
-            let paths = addon_file.file_paths_current(addon_id, !o.noop, &repo.conf);
-            match addon_file.validate_download(&paths, &repo.conf, api, &mut finalizers, cache_only) {
-                Ok(_) => match Finalize::finalize_drain(&mut finalizers) {
-                    Ok(_) => {},
-                    Err(e) => error!("Failed to download addon: {}",e)
-                },
-                Err(e) => error!("Failed to download addon: {}",e),
+            let mut paths = addon_file.file_paths_current(addon_id, !o.noop, &repo.conf);
+            if addon.disabled && !paths.disabled && !is_existing(&paths.path) {
+                // file missing, re-download to .disabled
+                paths = addon_file.file_paths_new(addon_id, true, &repo.conf);
             }
+            jobs.push((addon,addon_file,paths));
+        }
+    }
+
+    let http = api.http();
+
+    let prefetched = par_map(&jobs, repo.conf.download_concurrency, |(_,addon_file,paths)| {
+        addon_file.prefetch(paths, &repo.conf, &http, cache_only)
+    });
+
+    let mut failed = false;
+
+    for ((addon,addon_file,paths),prefetched) in jobs.iter().zip(prefetched) {
+        let result = prefetched
+            .and_then(|_| addon_file.validate_download(paths, &repo.conf, api, &mut finalizers, cache_only) );
+        if let Err(e) = result {
+            error!("Failed to download addon {}: {}",addon.slug,e);
+            failed = true;
         }
     }
 
+    if failed {
+        // dropping the finalizers reverts the downloads
+        error!("Download failed, no changes made");
+        return false;
+    }
+
+    unwrap_result_error!(Finalize::finalize_slice(&mut finalizers), |e|"Failed to download addons: {}",e);
+
     false
 }
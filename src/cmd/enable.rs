@@ -1,10 +1,43 @@
 use crate::Op;
 use crate::conf::Repo;
+use crate::print::error::unwrap_match;
+use crate::util::match_str::find_installed_mod_by_key;
+use crate::{warn,unwrap_result_error};
 
 pub fn main(
     o: &Op,
     repo: &mut Repo,
     addon: String,
 ) -> bool {
-    todo!()
+    let addon_id = unwrap_result_error!(unwrap_match(find_installed_mod_by_key(&addon,&repo.addons,false))).z;
+
+    let addon = repo.addons.get(&addon_id).unwrap();
+    let installed = addon.installed.as_ref().unwrap();
+
+    let paths = installed.file_paths_current(addon_id, !o.noop, &repo.conf);
+
+    if !paths.disabled && !addon.disabled {
+        eprintln!("Already enabled: {}{}",addon.slug,o.suffix());
+        return false;
+    }
+
+    for dep_id in installed.dependencies.iter_required() {
+        if let Some(dep) = repo.addons.get(&dep_id).filter(|d| d.disabled ) {
+            warn!("Required dependency is disabled: {}",dep.slug);
+        }
+    }
+
+    eprintln!("Enable: {}{}",addon.slug,o.suffix());
+
+    if !o.noop {
+        let new_paths = installed.file_paths_new(addon_id, false, &repo.conf);
+        unwrap_result_error!(
+            paths.rename_to(&new_paths),
+            |e|"Failed to enable addon: {}",e
+        );
+        repo.addons.get_mut(&addon_id).unwrap().disabled = false;
+        return true;
+    }
+
+    false
 }
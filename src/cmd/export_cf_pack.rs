@@ -0,0 +1,210 @@
+use std::fs::File;
+use std::io::{Seek, Write};
+use std::path::{Path, PathBuf};
+
+use zip::ZipWriter;
+use zip::write::FileOptions;
+
+use crate::addon::AddonID;
+use crate::addon::local::LocalAddon;
+use crate::addon::source::Source;
+use crate::api::API;
+use crate::conf::Repo;
+use crate::util::fs::{part_file_path, remove_if};
+use crate::{Op, dark_log, error, hard_error, unwrap_result_error, warn};
+use super::fill_cf_manifest::{CfManifest, process};
+
+pub fn main(
+    o: &Op,
+    api: &mut API,
+    repo: &Repo,
+    template: Option<PathBuf>,
+    output: PathBuf,
+    overrides: Vec<PathBuf>,
+    game_version: Option<String>,
+) -> bool {
+    let mut manifest: CfManifest = match &template {
+        Some(template) => {
+            let template_json = unwrap_result_error!(std::fs::read(template), |e|"Failed to read template: {}",e);
+            unwrap_result_error!(serde_jsonrc::from_slice(&template_json), |e|"Failed to decode template: {}",e)
+        },
+        None => default_manifest(repo, &output, game_version),
+    };
+
+    process(&mut manifest, repo);
+
+    let overrides_dir = manifest.other.get("overrides")
+        .and_then(|v| v.as_str() )
+        .unwrap_or("overrides")
+        .trim_matches('/')
+        .to_owned();
+
+    // undistributable addons can't be referenced in the manifest, so the file is shipped in overrides
+    let addon_ids: Vec<AddonID> = repo.addons.values()
+        .filter(|a| a.installed.is_some() && a.id.source() == Source::CurseForge )
+        .map(|a| a.id )
+        .collect();
+    let undistributable = unwrap_result_error!(api.undistributable_addons(&addon_ids), |e|"Failed to query addon distribution: {}",e);
+
+    // addons of other sources are also shipped in overrides
+    let mut bundled: Vec<&LocalAddon> = repo.addons.values()
+        .filter(|a| a.installed.is_some() && a.id.source() != Source::CurseForge )
+        .collect();
+    manifest.files.retain(|entry| {
+        let addon = entry.project_id
+            .filter(|id| undistributable.contains(&AddonID(*id)) )
+            .and_then(|id| repo.addons.get(&AddonID(id)) );
+        if let Some(addon) = addon {
+            warn!("Mod distribution not allowed, add to overrides: {}",addon.slug);
+            bundled.push(addon);
+        }
+        addon.is_none()
+    });
+    bundled.sort_by_key(|a| &a.slug.0 );
+
+    let overrides = if overrides.is_empty() {
+        repo.conf.pack_overrides.clone()
+    } else {
+        overrides
+    };
+
+    if o.noop {
+        eprintln!("Export: {} ({} addons, {} bundled){}",output.to_string_lossy(),manifest.files.len(),bundled.len(),o.suffix());
+        return false;
+    }
+
+    let part_path = part_file_path(&output);
+
+    let result = write_pack(&part_path, &manifest, repo, &bundled, &overrides_dir, &overrides)
+        .and_then(|_| std::fs::rename(&part_path, &output).map_err(Into::into) );
+
+    if let Err(e) = result {
+        let _ = remove_if(&part_path);
+        hard_error!("Failed to write modpack: {}",e);
+    }
+
+    eprintln!("Export: {} ({} addons, {} bundled)",output.to_string_lossy(),manifest.files.len(),bundled.len());
+
+    false
+}
+
+fn write_pack(
+    path: &Path,
+    manifest: &CfManifest,
+    repo: &Repo,
+    bundled: &[&LocalAddon],
+    overrides_dir: &str,
+    overrides: &[PathBuf],
+) -> anyhow::Result<()> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = FileOptions::default();
+
+    zip.start_file("manifest.json", options)?;
+    serde_jsonrc::to_writer_pretty(&mut zip, manifest)?;
+
+    zip.start_file("modlist.html", options)?;
+    zip.write_all(modlist_html(repo).as_bytes())?;
+
+    for addon in bundled {
+        let addon_file = addon.installed.as_ref().unwrap();
+        let paths = addon_file.file_paths_current(addon.id, false, &repo.conf);
+        let file_name = paths.path.file_name().unwrap().to_string_lossy().into_owned();
+        dark_log!("Bundle: {}",paths.path.to_string_lossy());
+        zip.start_file(format!("{overrides_dir}/mods/{file_name}"), options)?;
+        std::io::copy(&mut File::open(&paths.path)?, &mut zip)?;
+    }
+
+    for src in overrides {
+        let name = match src.file_name() {
+            Some(n) => format!("{overrides_dir}/{}",n.to_string_lossy()),
+            None => {error!("Invalid override path: {}",src.to_string_lossy());continue},
+        };
+        if !src.exists() {
+            warn!("Override not found, skipping: {}",src.to_string_lossy());
+            continue;
+        }
+        add_override(&mut zip, options, src, &name)?;
+    }
+
+    zip.finish()?;
+    Ok(())
+}
+
+fn add_override<W: Write + Seek>(zip: &mut ZipWriter<W>, options: FileOptions, src: &Path, name: &str) -> anyhow::Result<()> {
+    if src.is_dir() {
+        zip.add_directory(name, options)?;
+        let mut entries: Vec<_> = std::fs::read_dir(src)?.collect::<Result<_,_>>()?;
+        entries.sort_by_key(|e| e.file_name() );
+        for entry in entries {
+            add_override(zip, options, &entry.path(), &format!("{name}/{}",entry.file_name().to_string_lossy()))?;
+        }
+    } else {
+        dark_log!("Override: {}",src.to_string_lossy());
+        zip.start_file(name, options)?;
+        std::io::copy(&mut File::open(src)?, zip)?;
+    }
+    Ok(())
+}
+
+fn default_manifest(repo: &Repo, output: &Path, game_version: Option<String>) -> CfManifest {
+    let name = output.file_stem().map_or("modpack".into(), |n| n.to_string_lossy() );
+    // the manifest takes one exact version, patterns aren't understood by launchers
+    let game_version = match game_version {
+        Some(v) => {
+            if !repo.conf.game_version.matches_str(&v) {
+                warn!("Game version {} doesn't match the repo game version {}",v,repo.conf.game_version);
+            }
+            v
+        },
+        None => match repo.conf.game_version.exact() {
+            Some(v) => v.to_owned(),
+            None => hard_error!("Game version {} has no exact version, pass the version of the modpack with --game-version",repo.conf.game_version),
+        },
+    };
+    let template = serde_jsonrc::json!({
+        "minecraft": {
+            "version": game_version,
+            "modLoaders": [],
+        },
+        "manifestType": "minecraftModpack",
+        "manifestVersion": 1,
+        "name": name,
+        "version": "1.0.0",
+        "author": "",
+        "overrides": "overrides",
+        "files": [],
+    });
+    unwrap_result_error!(serde_jsonrc::from_value(template), |e|"Failed to create manifest: {}",e)
+}
+
+fn modlist_html(repo: &Repo) -> String {
+    let mut addons: Vec<&LocalAddon> = repo.addons.values()
+        .filter(|a| a.installed.is_some() && !a.disabled )
+        .collect();
+    addons.sort_by_key(|a| a.name.to_lowercase() );
+
+    let mut html = String::from("<ul>\n");
+    for addon in addons {
+        let url = match addon.id.source() {
+            Source::CurseForge => format!("https://www.curseforge.com/projects/{}",addon.id.0),
+            Source::Modrinth => format!("https://modrinth.com/mod/{}",addon.id.modrinth_id()),
+            Source::Direct => match &addon.installed.as_ref().unwrap().download_url {
+                Some(url) if url.local_path().is_none() => url.0.trim().to_owned(),
+                _ => {html += &format!("<li>{}</li>\n",escape_html(&addon.name));continue},
+            },
+        };
+        html += &format!(
+            "<li><a href=\"{}\">{} ({})</a></li>\n",
+            escape_html(&url), escape_html(&addon.name), escape_html(&addon.slug.0),
+        );
+    }
+    html += "</ul>\n";
+    html
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
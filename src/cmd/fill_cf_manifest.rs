@@ -3,6 +3,7 @@ use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
 
 use crate::addon::local::LocalAddon;
+use crate::addon::source::Source;
 use crate::conf::Repo;
 use crate::{error, warn, Op, unwrap_result_error};
 
@@ -19,6 +20,17 @@ pub fn main(
 
     process(&mut manifest, repo);
 
+    // addons of other sources can't be referenced in the manifest
+    let mut overrides: Vec<&str> = repo.addons.values()
+        .filter(|addon| addon.id.source() != Source::CurseForge )
+        .filter_map(|addon| addon.installed.as_ref() )
+        .map(|file| file.file_name.as_str() )
+        .collect();
+    overrides.sort_unstable();
+    for file_name in overrides {
+        warn!("Not in manifest, add to overrides: {}",file_name);
+    }
+
     let mut buf = Vec::with_capacity(1024*1024);
     unwrap_result_error!(serde_jsonrc::to_writer_pretty(&mut buf, &manifest), |e|"Failed to encode manifest: {}",e);
     unwrap_result_error!(std::fs::write(output,&buf), |e|"Failed to write manifest: {}",e);
@@ -26,12 +38,15 @@ pub fn main(
     false
 }
 
-fn process(manifest: &mut CfManifest, repo: &Repo) {
+pub fn process(manifest: &mut CfManifest, repo: &Repo) {
     if manifest.manifest_version.as_i64() != Some(1) {
         warn!("Unknown CfManifest template version ({})",manifest.manifest_version);
     }
 
-    let mut remaining_addons: Vec<&LocalAddon> = repo.addons.values().collect();
+    // addons from other sources can't be referenced in the manifest
+    let mut remaining_addons: Vec<&LocalAddon> = repo.addons.values()
+        .filter(|addon| addon.id.source() == Source::CurseForge )
+        .collect();
 
     for entry in &mut manifest.files {
         entry.handle_entry(&mut remaining_addons);
@@ -50,12 +65,12 @@ fn process(manifest: &mut CfManifest, repo: &Repo) {
 pub struct CfManifest {
     #[serde(rename = "manifestVersion")]
     #[serde(default)]
-    manifest_version: serde_jsonrc::Value,
+    pub manifest_version: serde_jsonrc::Value,
 
     #[serde(flatten)]
-    other: serde_jsonrc::Value,
+    pub other: serde_jsonrc::Value,
 
-    files: Vec<CfMFile>,
+    pub files: Vec<CfMFile>,
 }
 
 #[derive(Deserialize,Serialize)]
@@ -75,12 +90,13 @@ pub struct CfMFile {
     cursinator_slug: Option<String>,
 
     #[serde(rename = "projectID")]
-    project_id: Option<u64>,
+    pub project_id: Option<u64>,
 
     #[serde(rename = "fileID")]
-    file_id: Option<u64>,
+    pub file_id: Option<u64>,
 
-    required: Option<bool>,
+    /// disabled addons are not required
+    pub required: Option<bool>,
 
     #[serde(flatten)]
     other: serde_jsonrc::Value,
@@ -106,7 +122,7 @@ impl CfMFile {
                     self.file_id = Some(addon.installed.as_ref().unwrap().id.0);
                 }
                 if self.required.is_none() {
-                    self.required = Some(true);
+                    self.required = Some(!addon.disabled);
                 }
             } else {
                 error!("cursinator_slug not found: {slug}");
@@ -121,7 +137,7 @@ impl CfMFile {
                     self.file_id = Some(addon.installed.as_ref().unwrap().id.0);
                 }
                 if self.required.is_none() {
-                    self.required = Some(true);
+                    self.required = Some(!addon.disabled);
                 }
             } else {
                 error!("projectID not bound: {id}");
@@ -142,7 +158,7 @@ impl CfMFile {
             cursinator_slug: None,
             project_id: Some(addon.id.0),
             file_id: Some(addon.installed.as_ref().unwrap().id.0),
-            required: Some(true),
+            required: Some(!addon.disabled),
             other: serde_jsonrc::Value::Object(serde_jsonrc::Map::new()),
         }
     }
@@ -0,0 +1,213 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use rustc_hash::FxHashSet;
+
+use crate::addon::{AddonID, FileID, GameVersion};
+use crate::addon::local::{LocalAddon, UpdateOpt};
+use crate::addon::rtm::ReleaseTypeMode;
+use crate::conf::{Repo, repo_dir};
+use crate::util::fs::is_existing;
+use crate::{Op, dark_log, error, hard_error, log_error, unwrap_result_error, warn};
+use super::fill_cf_manifest::CfManifest;
+use super::init::new_repo;
+use super::new_api;
+
+pub fn main(
+    o: &Op,
+    input: PathBuf,
+) {
+    let is_zip = input.extension().map_or(false, |ext| ext.eq_ignore_ascii_case("zip") );
+
+    let mut zip = None;
+
+    let manifest: CfManifest = if is_zip {
+        let file = unwrap_result_error!(File::open(&input), |e|"Failed to open modpack: {}",e);
+        let mut archive = unwrap_result_error!(zip::ZipArchive::new(file), |e|"Failed to read modpack: {}",e);
+        let mut buf = Vec::with_capacity(1024*1024);
+        {
+            let mut manifest_file = unwrap_result_error!(archive.by_name("manifest.json"), |e|"Failed to find manifest.json in modpack: {}",e);
+            unwrap_result_error!(manifest_file.read_to_end(&mut buf), |e|"Failed to read manifest.json: {}",e);
+        }
+        zip = Some(archive);
+        unwrap_result_error!(serde_jsonrc::from_slice(&buf), |e|"Failed to decode manifest: {}",e)
+    } else {
+        let buf = unwrap_result_error!(std::fs::read(&input), |e|"Failed to read manifest: {}",e);
+        unwrap_result_error!(serde_jsonrc::from_slice(&buf), |e|"Failed to decode manifest: {}",e)
+    };
+
+    if manifest.manifest_version.as_i64() != Some(1) {
+        warn!("Unknown CfManifest version ({})",manifest.manifest_version);
+    }
+
+    let game_version = manifest.other.get("minecraft")
+        .and_then(|m| m.get("version") )
+        .and_then(|v| v.as_str() )
+        .map(|v| v.trim().to_owned() );
+
+    let mut repo = match Repo::load(&o.conf) {
+        Ok(Some(r)) => {
+            if let Some(game_version) = &game_version {
+                if repo_game_version_differs(&r, game_version) {
+                    warn!("Game version of modpack ({}) differs from repo ({})",game_version,r.conf.game_version);
+                }
+            }
+            r
+        },
+        Ok(None) => {
+            let game_version = unwrap_or_error_gv(game_version);
+            eprintln!("Initialize repo with game version {}{}",game_version,o.suffix());
+            let mut repo = new_repo(unwrap_result_error!(GameVersion::new([game_version]), |e|"Invalid game version in manifest: {}",e));
+            repo.conf.repo_dir = repo_dir(&o.conf);
+            repo
+        },
+        Err(e) => hard_error!("Failed to read repo json: {}",e),
+    };
+
+    let mut api = new_api(o, &repo);
+
+    // collect pinned files
+    let mut required = Vec::with_capacity(manifest.files.len());
+    for entry in &manifest.files {
+        match (entry.project_id,entry.file_id) {
+            (Some(p),Some(f)) => required.push((AddonID(p),FileID(f),entry.required.unwrap_or(true))),
+            _ => error!("Skipping manifest entry without projectID/fileID"),
+        }
+    }
+    required.retain(|(id,_,_)| {
+        let existing = repo.addons.get(id).filter(|a| a.installed.is_some() );
+        if let Some(existing) = existing {
+            warn!("Already in repo, skipping: {}",existing.slug);
+        }
+        existing.is_none()
+    });
+
+    let addon_ids: Vec<AddonID> = required.iter().map(|(id,_,_)| *id ).collect();
+    let file_ids: Vec<FileID> = required.iter().map(|(_,id,_)| *id ).collect();
+
+    let infos = unwrap_result_error!(api.addon_infos(&addon_ids), |e|"Failed to fetch addon info: {}",e);
+    let mut files = unwrap_result_error!(api.files_by_id(&file_ids), |e|"Failed to fetch addon files: {}",e);
+
+    // addons required by other addons of the pack are considered auto-installed
+    let dependencies: FxHashSet<AddonID> = files.iter()
+        .flat_map(|(_,file)| file.dependencies.iter_required() )
+        .collect();
+
+    let mut modified = false;
+
+    for (addon_id,file_id,required) in required {
+        let info = match infos.iter().find(|i| i.id == addon_id ) {
+            Some(i) => i,
+            None => {error!("Addon not available: {}",addon_id.0);continue},
+        };
+        let file = match files.iter().position(|(a,f)| *a == addon_id && f.id == file_id ) {
+            Some(i) => files.swap_remove(i).1,
+            None => {error!("Addon file not available: {} ({})",info.slug,file_id.0);continue},
+        };
+
+        eprintln!("Import: {} ({}){}",info.slug,file.file_name,o.suffix());
+
+        let addon = LocalAddon {
+            id: addon_id,
+            slug: info.slug.clone(),
+            name: info.name.clone(),
+            channel: ReleaseTypeMode::new(false,false,false),
+            update_opt: UpdateOpt::All,
+            manually_installed: !dependencies.contains(&addon_id),
+            version_blacklist: None,
+            positive_negative_in_filename: true,
+            installed: Some(file),
+            disabled: !required,
+            declined_optionals: vec![],
+            version_constraint: None,
+        };
+        o.plan().install(&addon, &repo.addons);
+        repo.addons.insert(addon_id, addon);
+        modified = true;
+    }
+
+    // extract overrides into the instance root, the parent of the addon dir, like export_cf_pack collects them
+    let overrides = manifest.other.get("overrides")
+        .and_then(|v| v.as_str() )
+        .unwrap_or("overrides")
+        .trim_matches('/')
+        .to_owned();
+    let dest = instance_dir(&o.conf);
+
+    if let Some(archive) = &mut zip {
+        for i in 0..archive.len() {
+            let mut entry = unwrap_result_error!(archive.by_index(i), |e|"Failed to read modpack: {}",e);
+            let path = match entry.enclosed_name().and_then(|p| p.strip_prefix(&overrides).ok() ) {
+                Some(p) if !p.as_os_str().is_empty() => dest.join(p),
+                _ => continue,
+            };
+            if entry.is_dir() {
+                if !o.noop {
+                    unwrap_result_error!(std::fs::create_dir_all(&path), |e|"Failed to extract overrides: {}",e);
+                }
+                continue;
+            }
+            if let Some(written) = override_target(o, &path) {
+                unwrap_result_error!(std::io::copy(&mut entry, &mut { written }), |e|"Failed to extract overrides: {}",e);
+            }
+        }
+    } else {
+        let src = input.parent().unwrap_or(Path::new(".")).join(&overrides);
+        if src.is_dir() {
+            copy_overrides(o, &src, &dest);
+        }
+    }
+
+    if (modified || repo.migrated_from.is_some()) && !o.noop {
+        dark_log!("Write repo json");
+        repo.sort_deps();
+        log_error!(repo.save(&o.conf),|e|"Failed to write repo json: {}",e);
+    }
+}
+
+fn unwrap_or_error_gv(game_version: Option<String>) -> String {
+    match game_version {
+        Some(v) => v,
+        None => hard_error!("No minecraft.version in manifest, initialize repo with init first"),
+    }
+}
+
+fn repo_game_version_differs(repo: &Repo, game_version: &str) -> bool {
+    !repo.conf.game_version.matches_str(game_version)
+}
+
+/// the parent of the addon dir, which contains the repo json
+fn instance_dir(conf: &Path) -> PathBuf {
+    repo_dir(conf).join("..")
+}
+
+/// create file for override, existing files are not overwritten
+fn override_target(o: &Op, path: &Path) -> Option<File> {
+    if is_existing(path) {
+        warn!("Override already exists, skipping: {}",path.to_string_lossy());
+        return None;
+    }
+    eprintln!("Override: {}{}",path.to_string_lossy(),o.suffix());
+    if o.noop {
+        return None;
+    }
+    if let Some(parent) = path.parent() {
+        unwrap_result_error!(std::fs::create_dir_all(parent), |e|"Failed to extract overrides: {}",e);
+    }
+    Some(unwrap_result_error!(File::create(path), |e|"Failed to extract overrides: {}",e))
+}
+
+fn copy_overrides(o: &Op, src: &Path, dest: &Path) {
+    let dir = unwrap_result_error!(std::fs::read_dir(src), |e|"Failed to read overrides: {}",e);
+    for entry in dir.flatten() {
+        let path = entry.path();
+        let dest = dest.join(entry.file_name());
+        if path.is_dir() {
+            copy_overrides(o, &path, &dest);
+        } else if let Some(mut written) = override_target(o, &dest) {
+            let mut file = unwrap_result_error!(File::open(&path), |e|"Failed to read overrides: {}",e);
+            unwrap_result_error!(std::io::copy(&mut file, &mut written), |e|"Failed to copy overrides: {}",e);
+        }
+    }
+}
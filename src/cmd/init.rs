@@ -1,35 +1,86 @@
+use std::io::{BufRead, IsTerminal, Write};
+
 use defaults::*;
 
-use crate::{Op, hard_assert, log_error, unwrap_or_error};
+use crate::{Op, hard_assert, log_error, unwrap_result_error};
 use crate::conf::*;
 use crate::addon::GameVersion;
+use crate::addon::loader::Loader;
 use crate::addon::local::LocalAddons;
 
 pub fn init(
     o: &Op,
     game_version: Option<String>,
     game_version_regex: Option<String>,
+    loader: Option<Loader>,
 ){
     hard_assert!(!o.conf.exists(),"repo already exists");
-    let game_version = unwrap_or_error!(game_version,"no game version (-g) defined");
+    hard_assert!(game_version.is_some() || game_version_regex.is_some(),"no game version (-g) or game version regex (-G) defined");
+
+    // -g can be a comma-separated list of versions, wildcards and ranges
+    let patterns = game_version.iter()
+        .flat_map(|g| g.split(',') )
+        .map(|g| g.trim().to_owned() )
+        .chain(game_version_regex.map(|r| format!("/{r}/") ));
+
+    let game_version = unwrap_result_error!(GameVersion::new(patterns), |e|"Invalid game version: {}",e);
+
+    let loader = loader.or_else(ask_loader);
+
+    let mut repo = new_repo(game_version);
+    if let Some(loader) = loader {
+        repo.conf.loader = Some(loader);
+        repo.conf.loader_fallbacks = loader.default_fallbacks();
+    }
+
+    if !o.noop {
+        log_error!(repo.save_new(&o.conf),|e|"Failed to write repo json: {}",e);
+    }
+}
 
-    let repo = Repo {
+pub fn new_repo(game_version: GameVersion) -> Repo {
+    Repo {
+        schema_version: migrate::SCHEMA_VERSION,
         conf: Conf {
-            game_version: GameVersion(game_version),
+            game_version,
             url_txt: default_url_txt(),
             addon_mtime: default_addon_mtime(),
             soft_retries: default_soft_retries(),
+            download_concurrency: default_download_concurrency(),
             api_headers: default_api_headers(),
             api_domain: default_api_domain(),
+            modrinth_api_domain: default_modrinth_api_domain(),
             override_api_key: None,
             symlink_cache_path: None, //Some("../cursinator_mod_cache".into())
+            cache_link_mode: Default::default(),
+            api_cache_path: None,
+            api_cache_ttl: default_api_cache_ttl(),
+            loader: None,
+            loader_fallbacks: vec![],
             positive_loader_filter: vec![],
             negative_loader_filter: vec![],
+            repo_dir: Default::default(),
+            pack_overrides: default_pack_overrides(),
         },
         addons: LocalAddons(Default::default()),
-    };
+        migrated_from: None,
+    }
+}
 
-    if !o.noop {
-        log_error!(repo.save_new(&o.conf),|e|"Failed to write repo json: {}",e);
+/// ask on the terminal, no loader if stdin isn't interactive or the answer is empty
+fn ask_loader() -> Option<Loader> {
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {return None;}
+
+    loop {
+        eprint!("Mod loader (forge, neoforge, fabric, quilt, empty for none): ");
+        std::io::stderr().flush().ok()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).ok()? == 0 {return None;}
+        if line.trim().is_empty() {return None;}
+        match Loader::from_name(&line) {
+            Some(l) => return Some(l),
+            None => eprintln!("Unknown mod loader: {}",line.trim()),
+        }
     }
 }
@@ -1,17 +1,17 @@
-use anyhow::bail;
+use anyhow::{anyhow, bail};
 
-use crate::{Op, unwrap_result_error};
+use crate::Op;
 use crate::addon::rtm::ReleaseTypeMode;
 use crate::api::API;
 use crate::conf::Repo;
 use crate::addon::AddonSlug;
-use crate::hard_error;
 use crate::api::files::FilesResult;
 use crate::util::match_str::find_to_install_version_by_key;
 use crate::print::error::unwrap_match;
+use crate::op::deps::{optional_deps, resolve_deps};
 use crate::op::update::find_version_update;
-use crate::op::install::install_mod;
-use crate::addon::local::UpdateOpt;
+use crate::op::install::{install_addons, install_mod};
+use crate::addon::local::{LocalAddon, UpdateOpt};
 
 pub fn main(
     o: &Op,
@@ -19,13 +19,71 @@ pub fn main(
     repo: &mut Repo,
     rt: Option<ReleaseTypeMode>,
     force: bool,
+    with_optional: bool,
     addon_query: String,
     version_blacklist: Option<String>,
 ) -> Result<bool,anyhow::Error> {
-    let (slug,version) = unwrap_result_error!(
-        decode_name_version(&addon_query),
-        |e| "Failed to decode addon query: {}",e
-    );
+    let addon = resolve(api, repo, rt, &addon_query, version_blacklist.as_deref())?;
+
+    install_mod(
+        addon.id,
+        addon.installed.unwrap(),
+        force,
+        addon.slug,
+        addon.name,
+        addon.channel,
+        addon.update_opt,
+        addon.manually_installed,
+        addon.version_blacklist,
+        addon.positive_negative_in_filename,
+        with_optional,
+        false,
+        o,
+        api,
+        repo,
+    )
+}
+
+/// Resolve all addons and their dependencies first, then install all or nothing
+pub fn main_atomic(
+    o: &Op,
+    api: &mut API,
+    repo: &mut Repo,
+    rt: Option<ReleaseTypeMode>,
+    force: bool,
+    with_optional: bool,
+    addon_queries: Vec<String>,
+    version_blacklist: Option<String>,
+) -> Result<bool,anyhow::Error> {
+    let mut plan: Vec<LocalAddon> = Vec::with_capacity(addon_queries.len());
+
+    for addon_query in &addon_queries {
+        let addon = resolve(api, repo, rt, addon_query, version_blacklist.as_deref())
+            .map_err(|e| anyhow!("{}: {}",addon_query,e) )?;
+        if plan.iter().any(|a| a.id == addon.id ) {
+            bail!("Addon requested twice: {}",addon.slug);
+        }
+        plan.push(addon);
+    }
+
+    // the dependencies of all requested addons are resolved together and queued before them
+    resolve_deps(&repo.addons, api, &repo.conf, vec![], &mut plan)?;
+
+    optional_deps(&repo.addons, api, &repo.conf, with_optional, &mut plan)?;
+
+    install_addons(plan, &[], force, o, api, repo)
+}
+
+/// find addon and the version to install
+fn resolve(
+    api: &mut API,
+    repo: &Repo,
+    rt: Option<ReleaseTypeMode>,
+    addon_query: &str,
+    version_blacklist: Option<&str>,
+) -> Result<LocalAddon,anyhow::Error> {
+    let (slug,version) = decode_name_version(addon_query)
+        .map_err(|e| anyhow!("Failed to decode addon query: {}",e) )?;
 
     // 1. get addon id
     let slug = AddonSlug(slug);
@@ -47,47 +105,55 @@ pub fn main(
         bail!("No version for current game version");
     }
 
-    if !versions.iter().any(|v| repo.conf.filter_addon_file(v, version_blacklist.as_deref(), true) ) {
+    if !versions.iter().any(|v| repo.conf.filter_addon_file(v, version_blacklist, true) ) {
         bail!("No version for current filter");
     }
 
     let channel = rt.unwrap_or_else(|| ReleaseTypeMode::new(false,false,false) ); //TODO use channel from previous install
 
+    // a constraint is kept from a previous install of the addon
+    let version_constraint = repo.addons.get(&addon_info.id).and_then(|a| a.version_constraint.clone() );
+
     let file;
     if let Some(version) = version {
         //TODO detect if version is a file id
         file = unwrap_match(find_to_install_version_by_key( &version, &versions,&repo.conf.game_version))?.z;
+        if let Some(constraint) = &version_constraint {
+            if !constraint.matches(file) {
+                bail!("{} is outside the version constraint {}",file.file_name,constraint);
+            }
+        }
     } else {
         let new = find_version_update(
             &versions,
             None,
             &repo.conf,
-            version_blacklist.as_deref(),
+            version_blacklist,
             true, //TODO
             channel,
             true,
+            version_constraint.as_ref(),
         );
         match new {
             Some(a) => file = a,
-            None => hard_error!("No version found to install"),
+            None => bail!("No version found to install"),
         }
     }
 
-    install_mod(
-        addon_info.id,
-        file.clone(),
-        force,
-        addon_info.slug,
-        addon_info.name,
+    Ok(LocalAddon {
+        id: addon_info.id,
+        slug: addon_info.slug,
+        name: addon_info.name,
         channel,
-        UpdateOpt::All, //TODO give as arg
-        true,
-        None, //TODO give vb as arg
-        true, //TODO
-        o,
-        api,
-        repo,
-    )
+        update_opt: UpdateOpt::All, //TODO give as arg
+        manually_installed: true,
+        version_blacklist: None, //TODO give vb as arg
+        positive_negative_in_filename: true, //TODO
+        installed: Some(file.clone()),
+        disabled: false,
+        declined_optionals: repo.addons.get(&addon_info.id).map_or(vec![], |a| a.declined_optionals.clone() ),
+        version_constraint,
+    })
 }
 
 fn decode_name_version(mut mod_req: &str) -> Result<(String,Option<String>),anyhow::Error> {
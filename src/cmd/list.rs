@@ -1,14 +1,23 @@
-use crate::Op;
+use crate::{Op, OutputFormat};
 use crate::addon::local::LocalAddon;
 use crate::conf::Repo;
 use crate::print::addons::print_addons_local;
+use crate::print::records::{AddonRecord, print_records};
 
 pub fn main(
-    _: &Op,
+    o: &Op,
     repo: &Repo,
 ) -> bool {
     let mut addons: Vec<&LocalAddon> = repo.addons.values().collect();
     addons.sort_unstable_by_key(|a| &a.slug.0 );
+    if o.format != OutputFormat::Text {
+        let records: Vec<AddonRecord> = addons.into_iter()
+            .filter(|a| a.installed.is_some() )
+            .map(AddonRecord::local)
+            .collect();
+        print_records(&records, o.format);
+        return false;
+    }
     print_addons_local(addons.into_iter());
     false
 }
@@ -1,9 +1,11 @@
 use crate::addon::release_type::ReleaseType;
 use crate::addon::rtm::ReleaseTypeMode;
-use crate::{Op, OpCmd, error, hard_error, log_error};
+use crate::{Op, OpCmd, OutputFormat, error, hard_error, log_error};
 use crate::util::match_str::match_str;
 use crate::conf::Repo;
+use crate::conf::cache_index::register_repo;
 use crate::api::{API, LazyFurse};
+use crate::api::cache::ApiCache;
 use crate::dark_log;
 
 pub mod aset;
@@ -23,11 +25,22 @@ pub mod disable;
 pub mod enable;
 pub mod search;
 pub mod download_all;
+pub mod sync;
 pub mod fill_cf_manifest;
+pub mod import_cf;
+pub mod export_cf_pack;
+pub mod adopt;
+pub mod add_url;
+pub mod cache;
+pub mod tree;
 
 pub fn main(o: Op) {
-    if let OpCmd::Init { game_version, game_version_regex } = o.cmd.clone() {
-        return init::init(&o,game_version,game_version_regex);
+    if let OpCmd::Init { game_version, game_version_regex, loader } = o.cmd.clone() {
+        return init::init(&o,game_version,game_version_regex,loader);
+    }
+    if let OpCmd::ImportCf { input } = o.cmd.clone() {
+        import_cf::main(&o,input);
+        return print_plan(&o);
     }
     
     let mut repo = match Repo::load(&o.conf) {
@@ -36,22 +49,21 @@ pub fn main(o: Op) {
         Err(e) => hard_error!("Failed to read repo json: {}",e),
     };
 
-    let mut api = API {
-        agent: ureq::Agent::new(),
-        retry_count: repo.conf.soft_retries.max(1),
-        //domain: repo.conf.api_domain.clone(),
-        headers: repo.conf.api_headers.clone(),
-        offline: o.offline,
-        furse: LazyFurse::new(&repo.conf),
-    };
+    let mut api = new_api(&o,&repo);
 
     let modified =
     match o.cmd.clone() {
-        OpCmd::Init { .. } => unreachable!(),
-        OpCmd::Install { alpha, beta, release, force, addons, version_blacklist } => {
+        OpCmd::Init { .. } | OpCmd::ImportCf { .. } => unreachable!(),
+        OpCmd::Install { alpha, beta, release, force, atomic, with_optional, addons, version_blacklist } if atomic => {
+            match install::main_atomic(&o,&mut api,&mut repo,ReleaseTypeMode::new2(release,beta,alpha),force,with_optional,addons,version_blacklist) {
+                Ok(v) => v,
+                Err(e) => {error!("Error installing mods, nothing installed: {}",e);false},
+            }
+        },
+        OpCmd::Install { alpha, beta, release, force, with_optional, addons, version_blacklist, .. } => {
             let mut modified = false;
             for a in addons {
-                match install::main(&o,&mut api,&mut repo,ReleaseTypeMode::new2(release,beta,alpha),force,a,version_blacklist.clone()) {
+                match install::main(&o,&mut api,&mut repo,ReleaseTypeMode::new2(release,beta,alpha),force,with_optional,a,version_blacklist.clone()) {
                     Ok(v) => modified |= v,
                     Err(e) => error!("Error installing mod: {}",e),
                 }
@@ -60,18 +72,24 @@ pub fn main(o: Op) {
         },
         OpCmd::Search { page_size, page_n, addon } =>
             search::main(&o,&mut api,&repo,page_size,page_n,addon),
-        OpCmd::Update { alpha, beta, release, allow_downgrade, force, addon, file } => 
-            update::main(&o,&mut api,&mut repo,ReleaseTypeMode::new2(release,beta,alpha),allow_downgrade,force,addon,file),
+        OpCmd::Update { alpha, beta, release, allow_downgrade, force, with_optional, autoremove, addon, file } => 
+            update::main(&o,&mut api,&mut repo,ReleaseTypeMode::new2(release,beta,alpha),allow_downgrade,force,with_optional,autoremove,addon,file),
         OpCmd::Channel { addon, value } => 
             channel::main(&o,&mut repo,addon,value),
         OpCmd::List {} => 
             list::main(&o,&repo),
         OpCmd::Updates { alpha, beta, release, show_all,older, addon } => 
             updates::main(&o,&mut api,&repo,ReleaseTypeMode::new2(release,beta,alpha),show_all,older,addon),
-        OpCmd::UpdateAll { alpha, beta, release } => 
-            update_all::main(&o,&mut api,&mut repo,ReleaseTypeMode::new2(release,beta,alpha)),
+        OpCmd::UpdateAll { alpha, beta, release, autoremove } => 
+            update_all::main(&o,&mut api,&mut repo,ReleaseTypeMode::new2(release,beta,alpha),autoremove),
         OpCmd::DownloadAll { cache_only } =>
             download_all::main(&o,&mut api,&repo,cache_only),
+        OpCmd::AddUrl { slug, sha1, sha256, source } =>
+            add_url::main(&o,&mut api,&mut repo,slug,sha1,sha256,source),
+        OpCmd::Adopt {} =>
+            adopt::main(&o,&mut api,&mut repo),
+        OpCmd::Sync {} =>
+            sync::main(&o,&mut api,&repo),
         OpCmd::Remove { force, addon } => 
             remove::main(&o,&mut repo,force,addon),
         OpCmd::AutoRemove { purge } => 
@@ -80,23 +98,66 @@ pub fn main(o: Op) {
             purge::main(&o,&mut repo,force,cleanup_only,addon),
         OpCmd::PurgeRemoved {} => 
             purge_removed::main(&o,&mut repo),
-        // OpCmd::Disable { addon, force, disable_depending } =>
-        //     disable::main(&o,&mut repo,addon),
-        // OpCmd::Enable { addon } => 
-        //     enable::main(&o,&mut repo,addon),
+        OpCmd::Disable { addon, force, disable_depending } =>
+            disable::main(&o,&mut repo,force,disable_depending,addon),
+        OpCmd::Enable { addon } => 
+            enable::main(&o,&mut repo,addon),
         OpCmd::Aset { addon, key, value } => 
             aset::main(&o,&mut repo,addon,key,value),
         OpCmd::Rset { key, value } => 
             rset::main(&o,&mut repo,key,value),
         OpCmd::GenCfManifest { input, output } =>
             fill_cf_manifest::main(&o, &repo, input, output),
+        OpCmd::ExportCfPack { template, overrides, game_version, output } =>
+            export_cf_pack::main(&o, &mut api, &repo, template, output, overrides, game_version),
+        OpCmd::Tree { reverse, addon } =>
+            tree::main(&o, &repo, addon, reverse),
+        OpCmd::Why { addon } =>
+            tree::why(&o, &repo, addon),
+        OpCmd::Cache { cmd } =>
+            cache::main(&o, &repo, cmd),
     };
 
-    if modified {
+    // migrated repo jsons are written even if nothing else changed
+    if modified || (repo.migrated_from.is_some() && !o.noop) {
         dark_log!("Write repo json");
         repo.sort_deps();
         log_error!(repo.save(&o.conf),|e|"Failed to write repo json: {}",e);
     }
+
+    if (modified || matches!(o.cmd, OpCmd::DownloadAll { .. } | OpCmd::Sync {})) && !o.noop {
+        log_error!(register_repo(&o.conf, &repo),|e|"Failed to update cache index: {}",e);
+    }
+
+    print_plan(&o);
+}
+
+fn print_plan(o: &Op) {
+    if o.format == OutputFormat::Json && has_plan(&o.cmd) {
+        o.plan().print_json();
+    }
+}
+
+/// commands which add, replace or remove addons
+fn has_plan(cmd: &OpCmd) -> bool {
+    matches!(cmd,
+        OpCmd::Install { .. } | OpCmd::Update { .. } | OpCmd::UpdateAll { .. } |
+        OpCmd::AddUrl { .. } | OpCmd::Adopt { .. } | OpCmd::Sync {} | OpCmd::ImportCf { .. } |
+        OpCmd::Remove { .. } | OpCmd::AutoRemove { .. } | OpCmd::Purge { .. } | OpCmd::PurgeRemoved { .. }
+    )
+}
+
+pub fn new_api(o: &Op, repo: &Repo) -> API {
+    API {
+        agent: ureq::Agent::new(),
+        retry_count: repo.conf.soft_retries.max(1),
+        //domain: repo.conf.api_domain.clone(),
+        headers: repo.conf.api_headers.clone(),
+        offline: o.offline,
+        furse: LazyFurse::new(&repo.conf),
+        modrinth_domain: repo.conf.modrinth_api_domain.clone(),
+        cache: ApiCache::new(&repo.conf, o.refresh),
+    }
 }
 
 pub fn release_type_from_flags(a: bool, b: bool, r: bool) -> Option<ReleaseType> {
@@ -38,6 +38,7 @@ pub fn main(
     if cleanup_only {
         let addon = &repo.addons.get(&addon_id).unwrap();
         if addon.installed.is_none() {
+            o.plan().remove(addon, true);
             eprintln!("Purging: {}{}",addon.slug,o.suffix());
             if !o.noop {
                 repo.addons.remove(&addon_id);
@@ -47,6 +48,7 @@ pub fn main(
             error!("Not purging installed addon (--cleanup-only)");
         }
     } else {
+        o.plan().remove(repo.addons.get(&addon_id).unwrap(), true);
         eprintln!("Purging: {}{}",slug,o.suffix());
 
         if !o.noop {
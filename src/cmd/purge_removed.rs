@@ -10,6 +10,7 @@ pub fn main(
     repo.addons.retain(|id,addon| {
         assert_eq!(id.0,addon.id.0);
         if addon.installed.is_none() {
+            o.plan().remove(addon, true);
             eprintln!("Purging: {}{}",addon.slug,o.suffix());
             if !o.noop {
                 modified = true;
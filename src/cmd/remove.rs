@@ -34,6 +34,7 @@ pub fn main(
         }
     }
 
+    o.plan().remove(repo.addons.get(&addon_id).unwrap(), false);
     eprintln!("Removing: {}{}",slug,o.suffix());
 
     if !o.noop {
@@ -1,11 +1,13 @@
-use crate::{Op, hard_error};
+use crate::{Op, OutputFormat, hard_error};
+use crate::addon::source::Source;
 use crate::api::API;
 use crate::conf::Repo;
 use crate::print::addons::print_addons_search;
+use crate::print::records::{AddonRecord, print_records};
 use crate::print::term_h;
 
 pub fn main(
-    _: &Op,
+    o: &Op,
     api: &mut API,
     repo: &Repo,
     mut page_size: u32,
@@ -17,8 +19,19 @@ pub fn main(
     }
     let page_n = page_n as u64 * page_size as u64;
 
-    match api.search_key(&addon,page_size as u64,page_n) {
-        Ok(v) => print_addons_search(v.iter(),&repo.conf.game_version,&repo.addons),
+    let result = match Source::from_query(&addon) {
+        (Source::Modrinth,key) => api.modrinth_search(key,page_size as u64,page_n),
+        (_,key) => api.search_key(key,page_size as u64,page_n),
+    };
+
+    match result {
+        Ok(v) if o.format != OutputFormat::Text => {
+            let records: Vec<AddonRecord> = v.iter()
+                .map(|a| AddonRecord::search(a, &repo.conf, &repo.addons) )
+                .collect();
+            print_records(&records, o.format);
+        },
+        Ok(v) => print_addons_search(v.iter(),&repo.conf,&repo.addons),
         Err(e) => hard_error!("Addon Search failed: {}",e),
     }
     false
@@ -0,0 +1,91 @@
+use std::path::PathBuf;
+
+use rustc_hash::FxHashSet;
+
+use crate::util::fs::{Finalize, attached_to_path, is_existing, remove_if};
+use crate::{Op, OutputFormat, error, unwrap_result_error};
+use crate::api::API;
+use crate::conf::Repo;
+
+pub fn main(
+    o: &Op,
+    api: &mut API,
+    repo: &Repo,
+) -> bool {
+    let mut finalizers: Vec<Finalize> = vec![];
+    let mut failed = false;
+
+    let mut locked_files = FxHashSet::default();
+
+    let mut addons: Vec<_> = repo.addons.values().collect();
+    addons.sort_unstable_by_key(|a| &a.slug.0 );
+
+    for addon in addons {
+        let addon_file = match &addon.installed {
+            Some(f) => f,
+            None => continue,
+        };
+        locked_files.insert(addon_file.file_name.as_str());
+
+        let mut paths = addon_file.file_paths_current(addon.id, !o.noop, &repo.conf);
+        if addon.disabled && !paths.disabled && !is_existing(&paths.path) {
+            paths = addon_file.file_paths_new(addon.id, true, &repo.conf);
+        }
+
+        // the plan is only checked ahead when it is shown, else validate_download checks it anyway
+        if o.noop || o.format == OutputFormat::Json {
+            if !matches!(addon_file.is_downloaded_valid(&paths), Ok(Some(_))) {
+                o.plan().download(addon);
+                if o.noop {
+                    eprintln!("Download: {} ({}){}",addon.slug,addon_file.file_name,o.suffix());
+                }
+            }
+        }
+        if o.noop {
+            continue;
+        }
+
+        if let Err(e) = addon_file.validate_download(&paths, &repo.conf, api, &mut finalizers, false) {
+            error!("Failed to download addon {}: {}",addon.slug,e);
+            failed = true;
+        }
+    }
+
+    if failed {
+        // dropping the finalizers reverts the downloads, exit would skip it
+        drop(finalizers);
+        error!("Sync failed, no changes made");
+        std::process::exit(1);
+    }
+
+    unwrap_result_error!(Finalize::finalize_slice(&mut finalizers), |e|"Failed to sync addons: {}",e);
+
+    // remove addon files not in the lock, which are identified by their .url.txt
+    let dir = unwrap_result_error!(std::fs::read_dir("."), |e|"Failed to read addon directory: {}",e);
+
+    for entry in dir.flatten() {
+        let name = entry.file_name();
+        let name = match name.to_str() {
+            Some(v) => v,
+            None => continue,
+        };
+        let file_name = match name.strip_suffix(".disabled.url.txt").or_else(|| name.strip_suffix(".url.txt") ) {
+            Some(v) => v,
+            None => continue,
+        };
+        if locked_files.contains(file_name) {continue}
+
+        eprintln!("Remove: {}{}",file_name,o.suffix());
+        o.plan().remove_file(file_name);
+
+        if !o.noop {
+            for path in [PathBuf::from(file_name), attached_to_path(file_name, ".disabled"), PathBuf::from(name)] {
+                if let Err(e) = remove_if(&path) {
+                    error!("Failed to remove {}: {}",path.to_string_lossy(),e);
+                }
+            }
+        }
+    }
+
+    false
+}
@@ -0,0 +1,326 @@
+use std::collections::VecDeque;
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde_jsonrc::{Value, json};
+
+use crate::addon::AddonID;
+use crate::addon::dependency::Dependency;
+use crate::addon::local::LocalAddons;
+use crate::conf::Repo;
+use crate::print::error::unwrap_match;
+use crate::util::match_str::find_installed_mod_by_key;
+use crate::{OutputFormat, Op, hard_error, unwrap_result_error};
+
+/// dependency edge between installed addons, or to a not installed addon
+#[derive(Clone,Copy,PartialEq)]
+struct Edge {
+    from: AddonID,
+    to: AddonID,
+    relation: &'static str,
+}
+
+struct Node {
+    id: AddonID,
+    relation: Option<&'static str>,
+    /// already shown above, children omitted
+    repeated: bool,
+    children: Vec<Node>,
+}
+
+pub fn main(
+    o: &Op,
+    repo: &Repo,
+    addon: Option<String>,
+    reverse: bool,
+) -> bool {
+    check_format(o.format);
+
+    let edges = edges(&repo.addons);
+
+    let roots = match addon {
+        Some(addon) => vec![unwrap_result_error!(unwrap_match(find_installed_mod_by_key(&addon,&repo.addons,false))).z],
+        None => {
+            let mut roots: Vec<AddonID> = repo.addons.values()
+                .filter(|a| a.installed.is_some() && (reverse || a.manually_installed) )
+                .map(|a| a.id )
+                .collect();
+            // addons not reached from any manually installed addon are also shown
+            if !reverse {
+                let mut reached = FxHashSet::default();
+                for &root in &roots {
+                    reachable(root, &edges, &mut reached);
+                }
+                roots.extend(
+                    repo.addons.values()
+                        .filter(|a| a.installed.is_some() && !reached.contains(&a.id) )
+                        .map(|a| a.id )
+                );
+            }
+            roots.sort_unstable_by_key(|id| label(*id, &repo.addons) );
+            roots
+        },
+    };
+
+    let mut expanded = FxHashSet::default();
+    let nodes: Vec<Node> = roots.into_iter()
+        .map(|id| build(id, None, &edges, reverse, &mut expanded, &mut vec![], &repo.addons) )
+        .collect();
+
+    match o.format {
+        OutputFormat::Text | OutputFormat::Tsv => {
+            for node in &nodes {
+                print_text(node, "", "", &repo.addons);
+            }
+        },
+        OutputFormat::Json => {
+            let v: Vec<Value> = nodes.iter().map(|n| node_json(n, &repo.addons) ).collect();
+            println!("{}",serde_jsonrc::to_string_pretty(&v).unwrap());
+        },
+        OutputFormat::Dot => {
+            let mut shown = vec![];
+            for node in &nodes {
+                collect_edges(node, reverse, &mut shown);
+            }
+            print_dot(&shown, &repo.addons);
+        },
+    }
+
+    false
+}
+
+fn check_format(format: OutputFormat) {
+    if format == OutputFormat::Tsv {
+        hard_error!("Format tsv is not supported by tree and why, use text, json or dot");
+    }
+}
+
+/// explain why an auto-installed addon is installed, by the shortest dependency path from each manually installed addon
+pub fn why(
+    o: &Op,
+    repo: &Repo,
+    addon: String,
+) -> bool {
+    check_format(o.format);
+
+    let target = unwrap_result_error!(unwrap_match(find_installed_mod_by_key(&addon,&repo.addons,false))).z;
+
+    let edges: Vec<Edge> = edges(&repo.addons).into_iter()
+        .filter(|e| e.relation == "required" )
+        .collect();
+
+    let mut roots: Vec<AddonID> = repo.addons.values()
+        .filter(|a| a.installed.is_some() && a.manually_installed )
+        .map(|a| a.id )
+        .collect();
+    roots.sort_unstable_by_key(|id| label(*id, &repo.addons) );
+
+    let paths: Vec<Vec<AddonID>> = roots.into_iter()
+        .filter_map(|root| shortest_path(root, target, &edges) )
+        .collect();
+
+    match o.format {
+        OutputFormat::Text | OutputFormat::Tsv => {
+            if paths.is_empty() {
+                println!("{} is not required by any manually installed addon",label(target, &repo.addons));
+            }
+            for path in &paths {
+                if path.len() == 1 {
+                    println!("{} is manually installed",label(target, &repo.addons));
+                } else {
+                    let labels: Vec<String> = path.iter().map(|id| label(*id, &repo.addons) ).collect();
+                    println!("{}",labels.join(" -> "));
+                }
+            }
+        },
+        OutputFormat::Json => {
+            let v: Vec<Vec<String>> = paths.iter()
+                .map(|path| path.iter().map(|id| label(*id, &repo.addons) ).collect() )
+                .collect();
+            println!("{}",serde_jsonrc::to_string_pretty(&v).unwrap());
+        },
+        OutputFormat::Dot => {
+            let mut shown = vec![];
+            for path in &paths {
+                for w in path.windows(2) {
+                    let edge = Edge { from: w[0], to: w[1], relation: "required" };
+                    if !shown.contains(&edge) {
+                        shown.push(edge);
+                    }
+                }
+            }
+            print_dot(&shown, &repo.addons);
+        },
+    }
+
+    false
+}
+
+fn edges(addons: &LocalAddons) -> Vec<Edge> {
+    let mut edges = vec![];
+    for addon in addons.values() {
+        if let Some(file) = &addon.installed {
+            for dep in file.dependencies.iter() {
+                let relation = match dep {
+                    Dependency::Required(_) => "required",
+                    Dependency::Optional(_) => "optional",
+                    Dependency::EmbeddedLibrary(_) => "embedded",
+                    Dependency::Incompatible(_) => "incompatible",
+                    _ => continue,
+                };
+                edges.push(Edge { from: addon.id, to: dep.id(), relation });
+            }
+        }
+    }
+    edges
+}
+
+fn reachable(id: AddonID, edges: &[Edge], dest: &mut FxHashSet<AddonID>) {
+    if !dest.insert(id) {return;}
+    for e in edges.iter().filter(|e| e.from == id && e.relation != "incompatible" ) {
+        reachable(e.to, edges, dest);
+    }
+}
+
+fn build(
+    id: AddonID,
+    relation: Option<&'static str>,
+    edges: &[Edge],
+    reverse: bool,
+    expanded: &mut FxHashSet<AddonID>,
+    path: &mut Vec<AddonID>,
+    addons: &LocalAddons,
+) -> Node {
+    let installed = addons.get(&id).map_or(false, |a| a.installed.is_some() );
+
+    // optional and incompatible edges are leaves, as they don't pull the addon in
+    let expand = installed && relation.map_or(true, |r| r == "required" || r == "embedded" );
+
+    if !expand || path.contains(&id) || !expanded.insert(id) {
+        let repeated = expand;
+        return Node { id, relation, repeated, children: vec![] };
+    }
+
+    let mut next: Vec<(AddonID,&'static str)> = edges.iter()
+        .filter_map(|e| if reverse {
+            (e.to == id).then_some((e.from,e.relation))
+        } else {
+            (e.from == id).then_some((e.to,e.relation))
+        })
+        .collect();
+    next.sort_unstable_by_key(|(id,relation)| (relation_order(relation),label(*id, addons)) );
+
+    path.push(id);
+    let children = next.into_iter()
+        .map(|(next,relation)| build(next, Some(relation), edges, reverse, expanded, path, addons) )
+        .collect();
+    path.pop();
+
+    Node { id, relation, repeated: false, children }
+}
+
+fn shortest_path(from: AddonID, to: AddonID, edges: &[Edge]) -> Option<Vec<AddonID>> {
+    let mut prev: FxHashMap<AddonID,AddonID> = FxHashMap::default();
+    let mut queue = VecDeque::from([from]);
+    let mut visited = FxHashSet::from_iter([from]);
+
+    while let Some(id) = queue.pop_front() {
+        if id == to {
+            let mut path = vec![to];
+            while let Some(&p) = prev.get(path.last().unwrap()) {
+                path.push(p);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for e in edges.iter().filter(|e| e.from == id ) {
+            if visited.insert(e.to) {
+                prev.insert(e.to, id);
+                queue.push_back(e.to);
+            }
+        }
+    }
+    None
+}
+
+fn relation_order(relation: &str) -> u8 {
+    match relation {
+        "required" => 0,
+        "embedded" => 1,
+        "optional" => 2,
+        _ => 3,
+    }
+}
+
+/// slug of installed addons, else the id
+fn label(id: AddonID, addons: &LocalAddons) -> String {
+    match addons.get(&id) {
+        Some(a) => a.slug.0.clone(),
+        None => id.to_string(),
+    }
+}
+
+fn print_text(node: &Node, prefix: &str, child_prefix: &str, addons: &LocalAddons) {
+    let mut line = prefix.to_owned();
+    if let Some(relation) = node.relation {
+        line += &format!("{relation}: ");
+    }
+    line += &label(node.id, addons);
+    if !addons.get(&node.id).map_or(false, |a| a.installed.is_some() ) {
+        line += " (not installed)";
+    } else if node.repeated {
+        line += " (*)";
+    }
+    println!("{line}");
+
+    for (i,child) in node.children.iter().enumerate() {
+        let last = i == node.children.len()-1;
+        print_text(
+            child,
+            &format!("{child_prefix}{}",if last {"└── "} else {"├── "}),
+            &format!("{child_prefix}{}",if last {"    "} else {"│   "}),
+            addons,
+        );
+    }
+}
+
+fn node_json(node: &Node, addons: &LocalAddons) -> Value {
+    json!({
+        "id": node.id.to_string(),
+        "slug": addons.get(&node.id).map(|a| a.slug.0.clone() ),
+        "relation": node.relation,
+        "installed": addons.get(&node.id).map_or(false, |a| a.installed.is_some() ),
+        "repeated": node.repeated,
+        "children": node.children.iter().map(|c| node_json(c, addons) ).collect::<Vec<_>>(),
+    })
+}
+
+fn collect_edges(node: &Node, reverse: bool, dest: &mut Vec<Edge>) {
+    for child in &node.children {
+        let edge = if reverse {
+            Edge { from: child.id, to: node.id, relation: child.relation.unwrap() }
+        } else {
+            Edge { from: node.id, to: child.id, relation: child.relation.unwrap() }
+        };
+        if !dest.contains(&edge) {
+            dest.push(edge);
+        }
+        collect_edges(child, reverse, dest);
+    }
+}
+
+fn print_dot(edges: &[Edge], addons: &LocalAddons) {
+    println!("digraph dependencies {{");
+    for e in edges {
+        let style = match e.relation {
+            "optional" => ", style=dashed",
+            "embedded" => ", style=dotted",
+            "incompatible" => ", color=red",
+            _ => "",
+        };
+        println!(
+            "    {:?} -> {:?} [label={:?}{}];",
+            label(e.from, addons), label(e.to, addons), e.relation, style,
+        );
+    }
+    println!("}}");
+}
@@ -9,6 +9,7 @@ use crate::print::error::unwrap_match;
 use crate::op::update::{find_version_update, fix_discrepancy};
 use crate::op::install::install_mod;
 use crate::addon::local::UpdateOpt;
+use crate::addon::source::Source;
 
 pub fn main(
     o: &Op,
@@ -17,6 +18,8 @@ pub fn main(
     rt: Option<ReleaseTypeMode>,
     allow_downgrade: bool,
     force: bool,
+    with_optional: bool,
+    autoremove: bool,
     addon: String,
     version: Option<String>,
 ) -> bool {
@@ -24,6 +27,10 @@ pub fn main(
 
     let addon = &repo.addons.get(&addon_id).unwrap();
 
+    if addon_id.source() == Source::Direct {
+        hard_error!("Direct addons can't be updated, use add-url to replace the file");
+    }
+
     let mut versions = match api.files(addon_id) {
         FilesResult::Ok(f) => f,
         FilesResult::NotFound => hard_error!("No online information for installed addon"),
@@ -47,10 +54,15 @@ pub fn main(
         //TODO detect if version is a file id
         file = unwrap_result_error!(unwrap_match(find_to_install_version_by_key( &version, &versions,&repo.conf.game_version))).z;
         if let Some(i) = addon.installed.as_ref() {
-            if file.id.0 < i.id.0 && !allow_downgrade {
+            if file.older_than(i) && !allow_downgrade {
                 hard_error!("Not downgrading");
             }
         }
+        if let Some(constraint) = &addon.version_constraint {
+            if !constraint.matches(file) {
+                hard_error!("{} is outside the version constraint {}",file.file_name,constraint);
+            }
+        }
     } else {
         if let UpdateOpt::Explicit = addon.update_opt {
             hard_error!("Addon update rule is set to explicit");
@@ -58,12 +70,13 @@ pub fn main(
 
         let new = find_version_update(
             &versions,
-            Some(addon.installed.as_ref().unwrap().id),
+            addon.installed.as_ref(),
             &repo.conf,
             addon.version_blacklist.as_deref(),
             addon.positive_negative_in_filename,
             channel,
             allow_downgrade,
+            addon.version_constraint.as_ref(),
         );
         match new {
             Some(a) => file = a,
@@ -82,6 +95,8 @@ pub fn main(
         addon.manually_installed,
         addon.version_blacklist.clone(), //TODO give vb as arg
         addon.positive_negative_in_filename,
+        with_optional,
+        autoremove,
         o,
         api,
         repo,
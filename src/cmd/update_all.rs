@@ -2,12 +2,15 @@ use std::collections::HashMap;
 
 use crate::Op;
 use crate::addon::local::UpdateOpt;
+use crate::addon::source::Source;
 use crate::addon::rtm::ReleaseTypeMode;
 use crate::api::API;
 use crate::api::files::FilesResult;
 use crate::conf::Repo;
 use crate::error;
-use crate::op::install::install_mod;
+use crate::addon::AddonID;
+use crate::addon::files::AddonFile;
+use crate::op::install::{install_mod, prefetch_addons};
 use crate::op::update::{find_version_update, fix_discrepancy};
 
 pub fn main(
@@ -15,6 +18,7 @@ pub fn main(
     api: &mut API,
     repo: &mut Repo,
     rt: Option<ReleaseTypeMode>,
+    autoremove: bool,
 ) -> bool {
 
     let mut cache = HashMap::with_capacity_and_hasher(256,Default::default());
@@ -37,10 +41,14 @@ pub fn main(
                 None => continue,
             };
 
+            if addon.id.source() == Source::Direct {
+                continue;
+            }
+
             let mut versions = match api.files_cached(addon.id,&mut cache) {
                 FilesResult::Ok(f) => f,
-                FilesResult::NotFound => {error!("No online information for installed addon");continue},
-                FilesResult::Error(e) => {error!("Failed to fetch online information: {}",e);continue},
+                FilesResult::NotFound => {error!("No online information for installed addon: {}",addon.slug);continue},
+                FilesResult::Error(e) => {error!("Failed to fetch online information for {}: {}",addon.slug,e);continue},
             };
 
             fix_discrepancy(&mut versions, installed);
@@ -55,12 +63,13 @@ pub fn main(
 
             let new = find_version_update(
                 &versions,
-                Some(installed.id),
+                Some(installed),
                 &repo.conf,
                 addon.version_blacklist.as_deref(),
                 addon.positive_negative_in_filename,
                 rt.unwrap_or(addon.channel),
                 false, //TODO allow_upgrade arg
+                addon.version_constraint.as_ref(),
             );
 
             if let Some(new) = new {
@@ -78,6 +87,14 @@ pub fn main(
             }
         }
 
+        if !o.noop {
+            let files: Vec<(AddonID,&AddonFile)> = queue.iter()
+                .map(|q| (q.0,&q.8) )
+                .collect();
+            // failed downloads are retried and reported by the install
+            let _ = prefetch_addons(&files, o, api, repo);
+        }
+
         for (
             id,
             slug,
@@ -100,6 +117,8 @@ pub fn main(
                 manually_installed,
                 version_blacklist, //TODO give vb as arg
                 positive_negative_in_filename,
+                false,
+                autoremove,
                 o,
                 api,
                 repo,
@@ -1,18 +1,20 @@
 use crate::addon::local::LocalAddon;
+use crate::addon::source::Source;
 use crate::op::update::{find_version_update, fix_discrepancy};
 use crate::print::addons::print_addon;
-use crate::{Op, error, hard_error, unwrap_result_error};
+use crate::{Op, OutputFormat, error, hard_error, unwrap_result_error};
 use crate::addon::rtm::ReleaseTypeMode;
 use crate::api::API;
 use crate::api::files::FilesResult;
 use crate::conf::Repo;
 use crate::print::error::unwrap_match;
+use crate::print::records::{AddonRecord, VersionRecord, print_records};
 use crate::print::versions::print_versions;
 use crate::util::match_str::find_installed_mod_by_key;
 use crate::print::{Koller, term_w, term_h};
 
 pub fn main(
-    _: &Op,
+    o: &Op,
     api: &mut API,
     repo: &Repo,
     rt: Option<ReleaseTypeMode>,
@@ -25,6 +27,10 @@ pub fn main(
 
         let addon = &repo.addons.get(&addon_id).unwrap();
 
+        if addon_id.source() == Source::Direct {
+            hard_error!("Direct addons have no online versions");
+        }
+
         let mut versions = match api.files(addon_id) {
             FilesResult::Ok(f) => f,
             FilesResult::NotFound => hard_error!("No online information for installed addon"),
@@ -41,6 +47,18 @@ pub fn main(
             hard_error!("No version for current filter");
         }
 
+        if o.format != OutputFormat::Text {
+            let installed = addon.installed.as_ref().unwrap();
+            let records: Vec<VersionRecord> = versions.iter()
+                .rev()
+                .filter(|v| list_older || !v.older_than(installed) )
+                .filter(|v| repo.conf.filter_addon_file(v, addon.version_blacklist.as_deref(), addon.positive_negative_in_filename) )
+                .map(|v| VersionRecord::new(v, Some(installed)) )
+                .collect();
+            print_records(&records, o.format);
+            return false;
+        }
+
         print_versions(
             &versions,
             Some(addon.installed.as_ref().unwrap()),
@@ -54,16 +72,21 @@ pub fn main(
     } else {
         let mut addons: Vec<&LocalAddon> = repo.addons.values().collect();
         addons.sort_unstable_by_key(|a| &a.slug.0 );
+        let mut records = vec![];
         for a in addons {
             let installed = match &a.installed {
                 Some(h) => h,
                 None => continue,
             };
 
+            if a.id.source() == Source::Direct {
+                continue;
+            }
+
             let mut versions = match api.files(a.id) {
                 FilesResult::Ok(f) => f,
-                FilesResult::NotFound => {error!("No online information for installed addon");continue},
-                FilesResult::Error(e) => {error!("Failed to fetch online information: {}",e);continue},
+                FilesResult::NotFound => {error!("No online information for installed addon: {}",a.slug);continue},
+                FilesResult::Error(e) => {error!("Failed to fetch online information for {}: {}",a.slug,e);continue},
             };
 
             fix_discrepancy(&mut versions, installed);
@@ -80,15 +103,20 @@ pub fn main(
 
             let new = find_version_update(
                 &versions,
-                Some(installed.id),
+                Some(installed),
                 &repo.conf,
                 a.version_blacklist.as_deref(),
                 a.positive_negative_in_filename,
                 a.channel,
                 list_older,
+                a.version_constraint.as_ref(),
             );
 
-            if let Some(new) = new {
+            if o.format != OutputFormat::Text {
+                if new.is_some() || show_all {
+                    records.push(AddonRecord::local(a).with_update(new));
+                }
+            } else if let Some(new) = new {
                 print_addon(
                     &a.slug,
                     &a.name,
@@ -110,6 +138,7 @@ pub fn main(
                 );
             }
         }
+        print_records(&records, o.format);
     }
     false
 }
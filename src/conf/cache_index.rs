@@ -0,0 +1,142 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{File, OpenOptions};
+use std::io::ErrorKind;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use serde_derive::*;
+
+use crate::addon::{AddonID, AddonSlug};
+use crate::addon::files::AddonFile;
+use crate::util::fs::{part_file_path, remove_if};
+use super::Repo;
+
+/// Index of the shared cache dir, records which repos reference which cache files
+#[derive(Deserialize,Serialize,Default)]
+pub struct CacheIndex {
+    /// repo json path => names of referenced cache files
+    pub repos: BTreeMap<PathBuf,BTreeSet<String>>,
+    /// cache file name => addon file stored in it
+    pub entries: BTreeMap<String,CacheEntry>,
+}
+
+#[derive(Deserialize,Serialize)]
+pub struct CacheEntry {
+    pub id: AddonID,
+    pub slug: AddonSlug,
+    pub file: AddonFile,
+}
+
+pub const INDEX_FILE: &str = "index.json";
+const INDEX_LOCK_FILE: &str = "index.lock";
+
+impl CacheIndex {
+    /// lock the index of the cache dir for the duration of f, the index is saved if f succeeds
+    pub fn with_locked<T>(cache_dir: &Path, f: impl FnOnce(&mut Self) -> anyhow::Result<T>) -> anyhow::Result<T> {
+        Self::locked(cache_dir, true, f)
+    }
+
+    /// like with_locked, but changes to the index are discarded
+    pub fn read_locked<T>(cache_dir: &Path, f: impl FnOnce(&mut Self) -> anyhow::Result<T>) -> anyhow::Result<T> {
+        Self::locked(cache_dir, false, f)
+    }
+
+    fn locked<T>(cache_dir: &Path, save: bool, f: impl FnOnce(&mut Self) -> anyhow::Result<T>) -> anyhow::Result<T> {
+        let _lock = IndexLock::acquire(cache_dir)?;
+
+        let mut index = Self::load(cache_dir)?;
+        let v = f(&mut index)?;
+        if save {
+            index.save(cache_dir)?;
+        }
+        Ok(v)
+    }
+
+    fn load(cache_dir: &Path) -> anyhow::Result<Self> {
+        match std::fs::read(cache_dir.join(INDEX_FILE)) {
+            Ok(f) => serde_jsonrc::from_slice(&f)
+                .map_err(|e| anyhow::anyhow!("Failed to read cache index: {}",e) ),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, cache_dir: &Path) -> anyhow::Result<()> {
+        let path = cache_dir.join(INDEX_FILE);
+        let part = part_file_path(&path);
+        std::fs::write(&part, serde_jsonrc::to_vec_pretty(self)?)?;
+        remove_if(&path)?;
+        std::fs::rename(part, path)?;
+        Ok(())
+    }
+
+    /// replace the references of the repo with its currently installed addons
+    pub fn register(&mut self, repo_path: PathBuf, repo: &Repo) {
+        let mut names = BTreeSet::new();
+        for a in repo.addons.values() {
+            if let Some(file) = &a.installed {
+                let name = file.cache_file_name(a.id);
+                self.entries.entry(name.clone()).or_insert_with(|| CacheEntry {
+                    id: a.id,
+                    slug: a.slug.clone(),
+                    file: file.clone(),
+                });
+                names.insert(name);
+            }
+        }
+        self.repos.insert(repo_path, names);
+    }
+
+    pub fn unregister(&mut self, repo_path: &Path) {
+        self.repos.remove(repo_path);
+    }
+
+    pub fn is_referenced(&self, name: &str) -> bool {
+        self.repos.values().any(|names| names.contains(name) )
+    }
+
+    /// drop entries of files not referenced by any repo
+    pub fn retain_referenced(&mut self) {
+        let referenced: BTreeSet<String> = self.repos.values().flatten().cloned().collect();
+        self.entries.retain(|name,_| referenced.contains(name) );
+    }
+}
+
+/// register the repo in the index of its cache dir
+pub fn register_repo(repo_path: &Path, repo: &Repo) -> anyhow::Result<()> {
+    if let Some(cache_dir) = &repo.conf.symlink_cache_path {
+        repo.conf.ensure_cache_dir()?;
+        let repo_path = std::fs::canonicalize(repo_path)?;
+        CacheIndex::with_locked(cache_dir, |index| {
+            index.register(repo_path, repo);
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+/// whether the file in the cache dir belongs to the index instead of the cache
+pub fn is_index_file(name: &str) -> bool {
+    name == INDEX_FILE || name == INDEX_LOCK_FILE || name == "index.json.part"
+}
+
+struct IndexLock(File);
+
+impl IndexLock {
+    fn acquire(cache_dir: &Path) -> anyhow::Result<Self> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .open(cache_dir.join(INDEX_LOCK_FILE))?;
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(Self(file))
+    }
+}
+
+impl Drop for IndexLock {
+    fn drop(&mut self) {
+        unsafe { libc::flock(self.0.as_raw_fd(), libc::LOCK_UN); }
+    }
+}
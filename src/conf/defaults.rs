@@ -1,9 +1,14 @@
+use std::path::PathBuf;
+
 pub fn default_api_headers() -> Vec<(String,String)> {
     vec![]
 }
 pub fn default_api_domain() -> String {
     "https://api.curseforge.com/v1".to_owned()
 }
+pub fn default_modrinth_api_domain() -> String {
+    "https://api.modrinth.com/v2".to_owned()
+}
 pub fn default_url_txt() -> bool {
     true
 }
@@ -13,3 +18,12 @@ pub fn default_addon_mtime() -> bool {
 pub fn default_soft_retries() -> u32 {
     4
 }
+pub fn default_pack_overrides() -> Vec<PathBuf> {
+    vec!["../config".into(),"../scripts".into()]
+}
+pub fn default_download_concurrency() -> usize {
+    4
+}
+pub fn default_api_cache_ttl() -> u64 {
+    3600
+}
@@ -0,0 +1,55 @@
+use std::path::{Path, PathBuf};
+
+use serde_derive::*;
+
+use crate::addon::{AddonID, AddonSlug};
+use crate::addon::files::AddonFile;
+use crate::addon::local::LocalAddons;
+use crate::warn;
+
+/// The resolved state of the repo (installed files with hashes, download urls and dependencies),
+/// stored next to the repo json, while the repo json only holds the addon settings.
+#[derive(Deserialize,Serialize)]
+pub struct RepoLock {
+    pub addons: Vec<LockedAddon>,
+}
+
+#[derive(Deserialize,Serialize)]
+pub struct LockedAddon {
+    pub id: AddonID,
+    pub slug: AddonSlug,
+    pub file: AddonFile,
+}
+
+/// repo.json => repo.lock
+pub fn lock_path(conf: impl AsRef<Path>) -> PathBuf {
+    conf.as_ref().with_extension("lock")
+}
+
+impl RepoLock {
+    pub fn from_addons(addons: &LocalAddons) -> Self {
+        let mut v: Vec<LockedAddon> = addons.values()
+            .filter_map(|a| a.installed.as_ref().map(|file| LockedAddon {
+                id: a.id,
+                slug: a.slug.clone(),
+                file: file.clone(),
+            }))
+            .collect();
+        v.sort_unstable_by(|a,b| a.slug.0.cmp(&b.slug.0) );
+        Self { addons: v }
+    }
+
+    /// the lock is authoritative over installed files, addons not in the lock are not installed
+    pub fn apply_to(self, addons: &mut LocalAddons) {
+        for a in addons.values_mut() {
+            a.installed = None;
+        }
+        for l in self.addons {
+            if let Some(a) = addons.get_mut(&l.id) {
+                a.installed = Some(l.file);
+            } else {
+                warn!("Addon in repo lock but not in repo json, ignoring: {}",l.slug);
+            }
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use serde_jsonrc::Value;
+
+use crate::addon::loader::Loader;
+use crate::dark_log;
+
+/// Schema version of repo jsons written by this version
+///
+/// Every change which older versions can't read needs a step in [`MIGRATIONS`], which upgrades from the previous version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// MIGRATIONS[n] upgrades the raw repo json from schema version n to n+1
+const MIGRATIONS: [fn(&mut Value) -> anyhow::Result<()>; SCHEMA_VERSION as usize] = [
+    loader_filters_to_loader,
+];
+
+/// schema version of the raw repo json, repo jsons from before the schema version are version 0
+pub fn schema_version(repo: &Value) -> anyhow::Result<u32> {
+    match repo.get("schema_version") {
+        None => Ok(0),
+        Some(v) => v.as_u64()
+            .and_then(|v| u32::try_from(v).ok() )
+            .ok_or_else(|| anyhow::anyhow!("Invalid schema_version: {}",v) ),
+    }
+}
+
+/// upgrade the raw repo json step by step to SCHEMA_VERSION, returns the version it was upgraded from
+pub fn migrate(repo: &mut Value) -> anyhow::Result<Option<u32>> {
+    let from = schema_version(repo)?;
+
+    if from > SCHEMA_VERSION {
+        anyhow::bail!("Repo json has schema version {}, but this version of cursinator only supports up to {}, update cursinator",from,SCHEMA_VERSION);
+    }
+    if from == SCHEMA_VERSION {
+        return Ok(None);
+    }
+
+    for version in from..SCHEMA_VERSION {
+        dark_log!("Migrate repo json from schema version {} to {}",version,version+1);
+        MIGRATIONS[version as usize](repo)
+            .map_err(|e| anyhow::anyhow!("Failed to migrate repo json from schema version {}: {}",version,e) )?;
+    }
+
+    let Some(repo) = repo.as_object_mut() else {anyhow::bail!("Repo json is not an object")};
+    repo.insert("schema_version".to_owned(), SCHEMA_VERSION.into());
+
+    Ok(Some(from))
+}
+
+/// 0 => 1: loader names in positive_loader_filter become the typed loader, the other ones the loader_fallbacks
+fn loader_filters_to_loader(repo: &mut Value) -> anyhow::Result<()> {
+    let Some(conf) = repo.get_mut("conf").and_then(Value::as_object_mut) else {anyhow::bail!("No conf in repo json")};
+
+    if conf.get("loader").map_or(false, |l| !l.is_null() ) {
+        return Ok(());
+    }
+
+    let is_loader = |v: &Value| v.as_str().and_then(Loader::from_name).is_some();
+
+    let mut found: Vec<Loader> = vec![];
+    for l in conf.get("positive_loader_filter")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_str().and_then(Loader::from_name) )
+    {
+        if !found.contains(&l) {
+            found.push(l);
+        }
+    }
+    // quilt repos accepting fabric mods had both in the filter
+    found.sort_by_key(|l| *l != Loader::Quilt );
+    let Some((&loader,fallbacks)) = found.split_first() else {return Ok(())};
+
+    let fallbacks = if fallbacks.is_empty() {loader.default_fallbacks()} else {fallbacks.to_vec()};
+
+    conf.insert("loader".to_owned(), serde_jsonrc::to_value(loader)?);
+    conf.insert("loader_fallbacks".to_owned(), serde_jsonrc::to_value(fallbacks)?);
+    for list in ["positive_loader_filter","negative_loader_filter"] {
+        if let Some(list) = conf.get_mut(list).and_then(Value::as_array_mut) {
+            list.retain(|v| !is_loader(v) );
+        }
+    }
+
+    dark_log!("Migrated loader filters to loader: {}",loader);
+
+    Ok(())
+}
+
+#[test]
+fn test_migrate() {
+    let mut repo: Value = serde_jsonrc::from_str(r#"{
+        "conf": {
+            "game_version": "1.20.1",
+            "positive_loader_filter": ["Fabric","Quilt"],
+            "negative_loader_filter": ["Forge","unofficial"]
+        },
+        "addons": {}
+    }"#).unwrap();
+
+    assert_eq!(migrate(&mut repo).unwrap(), Some(0));
+    assert_eq!(schema_version(&repo).unwrap(), SCHEMA_VERSION);
+    assert_eq!(repo["conf"]["loader"], "quilt");
+    assert_eq!(repo["conf"]["loader_fallbacks"], serde_jsonrc::json!(["fabric"]));
+    assert_eq!(repo["conf"]["positive_loader_filter"], serde_jsonrc::json!([]));
+    assert_eq!(repo["conf"]["negative_loader_filter"], serde_jsonrc::json!(["unofficial"]));
+
+    assert_eq!(migrate(&mut repo).unwrap(), None);
+
+    repo["schema_version"] = (SCHEMA_VERSION+1).into();
+    assert!(migrate(&mut repo).is_err());
+}
@@ -1,4 +1,7 @@
 pub mod defaults;
+pub mod lock;
+pub mod cache_index;
+pub mod migrate;
 
 use std::fs::{File, OpenOptions};
 use std::io::{BufWriter, ErrorKind};
@@ -7,14 +10,23 @@ use std::path::{Path, PathBuf};
 use serde_derive::*;
 
 use crate::addon::GameVersion;
+use crate::addon::loader::Loader;
 use crate::addon::local::LocalAddons;
-use crate::util::fs::{part_file_path, remove_if};
+use crate::dark_log;
+use crate::util::fs::{CacheLinkMode, part_file_path, remove_if};
 use defaults::*;
+use lock::{RepoLock, lock_path};
 
 #[derive(Deserialize,Serialize)]
 pub struct Repo {
+    /// see migrate::SCHEMA_VERSION
+    #[serde(default)]
+    pub schema_version: u32,
     pub conf: Conf,
     pub addons: LocalAddons,
+    /// schema version of the loaded repo json if it was migrated, the original files are backed up on the next save
+    #[serde(skip)]
+    pub migrated_from: Option<u32>,
 }
 
 #[derive(Deserialize,Serialize)]
@@ -26,50 +38,103 @@ pub struct Conf {
     pub addon_mtime: bool,
     #[serde(default="default_soft_retries")]
     pub soft_retries: u32,
+    /// Number of parallel downloads
+    #[serde(default="default_download_concurrency")]
+    pub download_concurrency: usize,
     #[serde(default="default_api_headers")]
     pub api_headers: Vec<(String,String)>,
     #[serde(default="default_api_domain")]
     pub api_domain: String,
+    #[serde(default="default_modrinth_api_domain")]
+    pub modrinth_api_domain: String,
 
     pub override_api_key: Option<String>,
 
     #[serde(default)]
     pub symlink_cache_path: Option<PathBuf>,
+    /// How cache files are placed into the addon dir: symlink, hardlink, reflink or copy
+    #[serde(default)]
+    pub cache_link_mode: CacheLinkMode,
+
+    /// Directory of the API response cache, defaults to ~/.cache/cursinator/api
+    #[serde(default)]
+    pub api_cache_path: Option<PathBuf>,
+    /// Seconds until cached API responses are queried again
+    #[serde(default="default_api_cache_ttl")]
+    pub api_cache_ttl: u64,
+
+    /// Mod loader of the repo, files of other loaders are skipped
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub loader: Option<Loader>,
+    /// Loaders whose files are also accepted, e.g. fabric for quilt
+    #[serde(default)]
+    pub loader_fallbacks: Vec<Loader>,
 
     #[serde(default)]
     pub positive_loader_filter: Vec<String>,
 
     #[serde(default)]
     pub negative_loader_filter: Vec<String>,
+
+    /// Directory of the repo json, vendored files are relative to it
+    #[serde(skip)]
+    pub repo_dir: PathBuf,
+
+    /// Paths added to the overrides of exported modpacks, relative to the addon directory
+    #[serde(default="default_pack_overrides")]
+    pub pack_overrides: Vec<PathBuf>,
 }
 
 impl Repo {
     pub fn load(conf: impl AsRef<Path>) -> anyhow::Result<Option<Self>> {
-        let f = match std::fs::read_to_string(conf) {
+        let f = match std::fs::read_to_string(&conf) {
             Ok(f) => f,
             Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
             Err(e) => return Err(e.into()),
         };
-        match serde_jsonrc::from_str(&f) {
-            Ok(c) => Ok(Some(c)),
-            Err(e) => Err(e.into()),
+        let mut value: serde_jsonrc::Value = serde_jsonrc::from_str(&f)?;
+        let migrated_from = migrate::migrate(&mut value)?;
+        let mut repo: Self = match migrated_from {
+            Some(_) => serde_jsonrc::from_value(value)?,
+            // deserialize from the source for line numbers in errors
+            None => serde_jsonrc::from_str(&f)?,
+        };
+        repo.migrated_from = migrated_from;
+        repo.conf.repo_dir = repo_dir(conf.as_ref());
+        // repos without lock still have the installed files inline in the repo json
+        match std::fs::read_to_string(lock_path(&conf)) {
+            Ok(f) => {
+                let lock: RepoLock = serde_jsonrc::from_str(&f)
+                    .map_err(|e| anyhow::anyhow!("Failed to read repo lock: {}",e) )?;
+                lock.apply_to(&mut repo.addons);
+            },
+            Err(e) if e.kind() == ErrorKind::NotFound => {},
+            Err(e) => return Err(e.into()),
         }
+        Ok(Some(repo))
     }
     pub fn save(&self, conf: impl AsRef<Path>) -> anyhow::Result<()> {
-        let conf_part = part_file_path(conf.as_ref());
+        if let Some(from) = self.migrated_from {
+            backup_files(conf.as_ref(), from)?;
+        }
 
         let mut buf = Vec::with_capacity(1024*1024);
         serde_jsonrc::to_writer_pretty(&mut buf, self)?;
-        std::fs::write(&conf_part, buf)?; //TODO fix, .part
+        write_part_rename(conf.as_ref(), &buf)?;
 
-        remove_if(&conf)?;
-        std::fs::rename(conf_part, conf)?;
+        buf.clear();
+        serde_jsonrc::to_writer_pretty(&mut buf, &RepoLock::from_addons(&self.addons))?;
+        write_part_rename(&lock_path(conf), &buf)?;
         Ok(())
     }
     pub fn save_new(&self, conf: impl AsRef<Path>) -> anyhow::Result<()> {
         let mut buf = Vec::with_capacity(1024*1024);
         serde_jsonrc::to_writer_pretty(&mut buf, self)?;
-        std::fs::write(conf, buf)?;
+        std::fs::write(&conf, &buf)?;
+
+        buf.clear();
+        serde_jsonrc::to_writer_pretty(&mut buf, &RepoLock::from_addons(&self.addons))?;
+        std::fs::write(lock_path(conf), &buf)?;
         Ok(())
     }
     pub fn sort_deps(&mut self) {
@@ -81,6 +146,34 @@ impl Repo {
     }
 }
 
+/// repo.json => repo.v0.json.bak, an existing backup of the same version is kept
+fn backup_files(conf: &Path, schema_version: u32) -> anyhow::Result<()> {
+    for path in [conf.to_owned(),lock_path(conf)] {
+        let ext = path.extension().map_or_else(Default::default, |e| e.to_string_lossy() );
+        let backup = path.with_extension(format!("v{}.{}.bak",schema_version,ext));
+        if !path.exists() || backup.exists() {continue;}
+        dark_log!("Backup {} to {}",path.to_string_lossy(),backup.to_string_lossy());
+        std::fs::copy(&path, &backup)?;
+    }
+    Ok(())
+}
+
+/// directory of the repo json
+pub fn repo_dir(conf: &Path) -> PathBuf {
+    match conf.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_owned(),
+        _ => PathBuf::from("."),
+    }
+}
+
+fn write_part_rename(path: &Path, buf: &[u8]) -> anyhow::Result<()> {
+    let part = part_file_path(path);
+    std::fs::write(&part, buf)?;
+    remove_if(path)?;
+    std::fs::rename(part, path)?;
+    Ok(())
+}
+
 fn file_write(p: impl AsRef<Path>) -> std::io::Result<File> {
     OpenOptions::new()
         .write(true)
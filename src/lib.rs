@@ -10,10 +10,14 @@ pub mod retrieve_api_key;
 //#[cfg(not(target_pointer_width = "64"))]
 //compile_error!("only 64-bit pointer arch supported");
 
+use std::cell::RefCell;
 use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
 
+use addon::loader::Loader;
+use op::plan::Plan;
+
 #[derive(Parser)]
 #[command(name = "cursinator", about = "Download and manage CurseForge addons")]
 pub struct Op {
@@ -28,19 +32,32 @@ pub struct Op {
     pub noop: bool,
     /// No queries to online api
     #[arg(long)]
-    pub offline: bool, //TODO bork all API when offline mode
+    pub offline: bool,
+    /// Bypass the API response cache
+    #[arg(long)]
+    pub refresh: bool,
+    /// Output format, list/updates/search/channel print records to stdout, tree/why print graphs, with json the plan of mutating commands is printed, e.g. with --noop as dry-run
+    #[arg(long,global=true,value_enum,default_value="text")]
+    pub format: OutputFormat,
     #[command(subcommand)]
     pub cmd: OpCmd,
+    #[arg(skip)]
+    pub plan: RefCell<Plan>,
 }
 #[derive(Subcommand,Clone)]
 pub enum OpCmd {
     /// Initialize local mod repo
     #[command()]
     Init {
-        #[arg(short='g',long,help="gv")]
+        /// Game version, comma-separated list of versions, wildcards like 1.20.x and ranges like 1.20.1..=1.20.4
+        #[arg(short='g',long)]
         game_version: Option<String>,
-        #[arg(short='G',long,help="gv")]
+        /// Game version regex, matched in addition to -g
+        #[arg(short='G',long)]
         game_version_regex: Option<String>,
+        /// Mod loader, asked if not given
+        #[arg(short='l',long,value_enum)]
+        loader: Option<Loader>,
     },
     /// Search online for addon
     #[command()]
@@ -51,7 +68,8 @@ pub enum OpCmd {
         /// page index
         #[arg(short='n',long="page-n",default_value="0")]
         page_n: u32,
-        #[arg(help="addon")]
+        /// Search key, prefix with mr: to search on Modrinth
+        #[arg()]
         addon: String,
     },
     /// Install addon
@@ -69,10 +87,16 @@ pub enum OpCmd {
         /// Install even if incompatibility occurs
         #[arg(short='f',long)]
         force: bool,
+        /// Resolve all addons and their dependencies first and only install if everything succeeds
+        #[arg(long)]
+        atomic: bool,
+        /// Also install optional dependencies, else they are only listed
+        #[arg(long="with-optional")]
+        with_optional: bool,
         /// version blacklist
         #[arg(short='x',long="version-blacklist")]
         version_blacklist: Option<String>,
-        // Addon slug or id, with optional version specified, must be non-ambiguous
+        // Addon slug or id, with optional version specified, must be non-ambiguous. Prefix with mr: for Modrinth addons
         #[arg()]
         addons: Vec<String>,
     },
@@ -94,6 +118,12 @@ pub enum OpCmd {
         /// Allow downgrade (explicit version install always allows downgrade)
         #[arg(short='d',long="allow-downgrade")]
         allow_downgrade: bool,
+        /// Also install optional dependencies, else they are only listed
+        #[arg(long="with-optional")]
+        with_optional: bool,
+        /// Remove dependencies which aren't required anymore by the new version
+        #[arg(long)]
+        autoremove: bool,
         /// Match addon slug, id or installed filename, must be non-ambiguous
         #[arg()]
         addon: String, // if "list", do list -u
@@ -168,6 +198,9 @@ pub enum OpCmd {
         /// Override addon's channel for this operation only
         #[arg(short,long)]
         release: bool,
+        /// Remove dependencies which aren't required anymore by the new versions
+        #[arg(long)]
+        autoremove: bool,
     },
     /// Download all addons if not already downloaded or invalid
     #[command(name = "download-all")]
@@ -175,6 +208,34 @@ pub enum OpCmd {
         /// Only fill cache
         #[arg(short,long)]
         cache_only: bool,
+    },
+    /// Add addon from URL or vendored local file, which isn't tracked by any platform
+    /// 
+    /// The file is pinned by its hash and never updated. Add again with the same slug to replace the file.
+    #[command(name = "add-url")]
+    AddUrl {
+        /// Slug of the addon, derived from the file name if not given
+        #[arg(short,long)]
+        slug: Option<String>,
+        /// Expected SHA-1 of the file
+        #[arg(long)]
+        sha1: Option<String>,
+        /// Expected SHA-256 of the file
+        #[arg(long)]
+        sha256: Option<String>,
+        /// http(s) URL or path to file
+        #[arg()]
+        source: String,
+    },
+    /// Register unknown .jar files in the addon directory by matching their fingerprint
+    #[command()]
+    Adopt {
+        
+    },
+    /// Download and remove addon files to match the repo lock exactly, no API queries besides downloads
+    #[command()]
+    Sync {
+        
     },
     /// Remove addon. Use purge to also remove metadata/information/settings of the addon
     #[command()]
@@ -211,26 +272,26 @@ pub enum OpCmd {
     PurgeRemoved {
         
     },
-    // /// Rename addon to .disabled
-    // #[command()]
-    // Disable { //TODO check for dependent addons
-    //     /// Disable addon even if other addons depend on this addon
-    //     #[arg(short='f',long)]
-    //     force: bool,
-    //     /// Also disable addons that depend on this addons recursively
-    //     #[arg(long="disable-depending")]
-    //     disable_depending: bool,
-    //     /// TODO
-    //     #[arg()]
-    //     addon: String,
-    // },
-    // /// Enable .disabled addon
-    // #[command()]
-    // Enable {
-    //     /// TODO
-    //     #[arg()]
-    //     addon: String,
-    // },
+    /// Rename addon to .disabled
+    #[command()]
+    Disable {
+        /// Disable addon even if other addons depend on this addon
+        #[arg(short='f',long)]
+        force: bool,
+        /// Also disable addons that depend on this addons recursively
+        #[arg(long="disable-depending")]
+        disable_depending: bool,
+        /// Match addon slug, id or installed filename which should be disabled, must be non-ambiguous
+        #[arg()]
+        addon: String,
+    },
+    /// Enable .disabled addon
+    #[command()]
+    Enable {
+        /// Match addon slug, id or installed filename which should be enabled, must be non-ambiguous
+        #[arg()]
+        addon: String,
+    },
     /// Addon setting. Not all options exposed yet, refer repo.json
     #[command()]
     Aset{ //TODO move update_opt to separate option
@@ -254,6 +315,16 @@ pub enum OpCmd {
         #[arg()]
         value: Option<String>,
     },
+    /// Import CurseForge modpack zip or manifest.json, initializes the repo if it doesn't exist
+    /// 
+    /// Files of the overrides folder are extracted into the instance root, the parent of the addon dir with the repo json, existing files are kept.
+    /// Addon files are not downloaded, use download-all or sync afterwards.
+    #[command(name = "import-cf")]
+    ImportCf {
+        /// Modpack zip or manifest.json
+        #[arg()]
+        input: PathBuf,
+    },
     /// Generate CF manifest.json from template
     #[command(name = "gen-cf-manifest")]
     GenCfManifest {
@@ -264,4 +335,72 @@ pub enum OpCmd {
         #[arg()]
         output: PathBuf,
     },
+    /// Export CurseForge modpack zip with manifest.json, modlist.html and overrides
+    /// 
+    /// Undistributable addons are added to the overrides instead of the manifest.
+    #[command(name = "export-cf-pack")]
+    ExportCfPack {
+        /// Manifest template, see gen-cf-manifest
+        #[arg(short,long)]
+        template: Option<PathBuf>,
+        /// Paths to add to the overrides, replaces pack_overrides of repo conf
+        #[arg(short='O',long="override")]
+        overrides: Vec<PathBuf>,
+        /// Exact game version of the manifest, required if the game version of the repo has none
+        #[arg(short='g',long)]
+        game_version: Option<String>,
+        /// Output modpack zip
+        #[arg()]
+        output: PathBuf,
+    },
+    /// Show dependency tree of installed addons
+    /// 
+    /// Without addon, the trees of all manually installed addons are shown
+    #[command()]
+    Tree {
+        /// Show the addons depending on the addon instead
+        #[arg(short,long)]
+        reverse: bool,
+        /// Match addon slug, id or installed filename, must be non-ambiguous
+        #[arg()]
+        addon: Option<String>,
+    },
+    /// Explain why addon is installed, by the paths from manually installed addons to it
+    #[command()]
+    Why {
+        /// Match addon slug, id or installed filename, must be non-ambiguous
+        #[arg()]
+        addon: String,
+    },
+    /// Manage the shared cache of symlink_cache_path
+    #[command()]
+    Cache {
+        #[command(subcommand)]
+        cmd: CacheCmd,
+    },
+}
+#[derive(clap::ValueEnum,Clone,Copy,PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    /// tab-separated, with header line
+    Tsv,
+    /// Graphviz DOT, for tree and why
+    Dot,
+}
+#[derive(Subcommand,Clone)]
+pub enum CacheCmd {
+    /// Remove cache files which aren't referenced by any repo using the cache
+    #[command()]
+    Gc {
+        /// Also remove files not in the cache index, which may belong to repos not registered yet
+        #[arg(long)]
+        force: bool,
+    },
+    /// Re-hash cache files and remove invalid ones
+    #[command()]
+    Verify {},
+    /// Show cache size per addon
+    #[command()]
+    Stats {},
 }
@@ -1,14 +1,14 @@
 use rustc_hash::FxHashSet;
 
 use crate::addon::AddonID;
-use crate::addon::local::LocalAddons;
+use crate::addon::local::{LocalAddon, LocalAddons};
 
 pub fn autoremovable(addons: &LocalAddons) -> Vec<AddonID> {
-    // collect addons which have dependents (are dep in other addons)
+    // collect addons which have dependents (are dep in other addons), installed optional deps also count
     let mut has_dependents = FxHashSet::default();
     for addon in addons.values() {
         if let Some(file) = &addon.installed {
-            for deps in file.dependencies.iter_required() {
+            for deps in file.dependencies.iter_required().chain(file.dependencies.iter_optional()) {
                 has_dependents.insert(deps);
             }
         }
@@ -24,3 +24,53 @@ pub fn autoremovable(addons: &LocalAddons) -> Vec<AddonID> {
     
     dest
 }
+
+/// auto-installed addons which no addon requires anymore after installing the queue, starting from the dependencies the replaced versions required
+/// 
+/// in removal order, dependencies only required by removed addons are included
+pub fn orphaned_deps(queue: &[LocalAddon], addons: &LocalAddons) -> Vec<AddonID> {
+    let mut candidates: Vec<AddonID> = vec![];
+    for new in queue {
+        if let Some(old) = addons.get(&new.id).and_then(|a| a.installed.as_ref() ) {
+            let new_file = new.installed.as_ref().unwrap();
+            candidates.extend(
+                old.dependencies.iter_required()
+                    .filter(|d| !new_file.dependencies.iter_required().any(|n| n == *d ) )
+            );
+        }
+    }
+
+    let mut dest: Vec<AddonID> = vec![];
+
+    loop {
+        // the files installed after the queue, without the already orphaned
+        let files = addons.values()
+            .filter(|a| !queue.iter().any(|q| q.id == a.id ) )
+            .chain(queue)
+            .filter(|a| !dest.contains(&a.id) )
+            .filter_map(|a| a.installed.as_ref() );
+
+        let mut has_dependents = FxHashSet::default();
+        for file in files {
+            for dep in file.dependencies.iter_required().chain(file.dependencies.iter_optional()) {
+                has_dependents.insert(dep);
+            }
+        }
+
+        let orphaned: Vec<AddonID> = candidates.iter()
+            .copied()
+            .filter(|id| !dest.contains(id) && !has_dependents.contains(id) && !queue.iter().any(|q| q.id == *id ) )
+            .filter(|id| addons.get(id).map_or(false, |a| a.installed.is_some() && !a.manually_installed ) )
+            .collect();
+
+        if orphaned.is_empty() {break}
+
+        for id in orphaned {
+            if dest.contains(&id) {continue}
+            candidates.extend(addons.get(&id).unwrap().installed.as_ref().unwrap().dependencies.iter_required());
+            dest.push(id);
+        }
+    }
+
+    dest
+}
@@ -1,99 +1,487 @@
 use anyhow::bail;
+use rustc_hash::FxHashMap;
 
+use crate::addon::files::AddonFile;
 use crate::addon::rtm::ReleaseTypeMode;
-use crate::addon::{AddonID, GameVersion};
+use crate::addon::{AddonID, AddonSlug};
 use crate::addon::local::{LocalAddon, LocalAddons, UpdateOpt};
 use crate::api::API;
 use crate::conf::Conf;
-use crate::unwrap_or_bail;
+use crate::warn;
 use crate::api::files::FilesResult;
 
-pub fn collect_deps(
+/// a required dependency to resolve, with the settings of the addon requiring it
+#[derive(Clone)]
+pub struct DepRequest {
+    pub id: AddonID,
+    pub parent: AddonSlug,
+    /// file date of the parent if an older version of it was picked, dependency versions up to this date are preferred
+    pub before: Option<String>,
+    pub channel: ReleaseTypeMode,
+    pub update_opt: UpdateOpt,
+    pub version_blacklist: Option<String>,
+    pub positive_negative_in_filename: bool,
+}
+
+impl DepRequest {
+    pub fn new(id: AddonID, parent: &LocalAddon, before: Option<String>) -> Self {
+        Self {
+            id,
+            parent: parent.slug.clone(),
+            before,
+            channel: parent.channel,
+            update_opt: parent.update_opt,
+            version_blacklist: parent.version_blacklist.clone(),
+            positive_negative_in_filename: parent.positive_negative_in_filename,
+        }
+    }
+}
+
+/// Resolve the required dependencies of the queued addons and the extra requested dependencies, and queue the picked versions before the queued addons
+/// 
+/// Searches over the legal versions of all dependencies, in order of channel preference, and backtracks if a version is incompatible with installed or queued addons.  
+/// If there is only a solution with incompatibilities, it is queued anyway and the install decides with --force.  
+/// Failures are explained by a minimal set of requests which can't be resolved together.
+pub fn resolve_deps(
     installed: &LocalAddons,
     api: &mut API,
-    deps: impl Iterator<Item=AddonID>,
     conf: &Conf,
-    channel: ReleaseTypeMode,
-    update_opt: UpdateOpt,
-    version_blacklist: &Option<String>,
-    positive_negative_in_filename: bool,
+    extra: Vec<DepRequest>,
     install_queue: &mut Vec<LocalAddon>,
 ) -> Result<(),anyhow::Error> {
-    // version picking for deps:
-    // 1. if explicit version for parent, filter to-install dep versions before specific date
-    //for dep in 
+    let start = install_queue.len();
+
+    let mut resolver = Resolver {
+        installed,
+        api,
+        conf,
+        addons: FxHashMap::default(),
+        steps: 0,
+        conflict: None,
+    };
+
+    let mut requests = vec![];
+    for i in 0..start {
+        let addon = &install_queue[i];
+        let missing: Vec<AddonID> = addon.installed.as_ref().unwrap().dependencies.iter_required()
+            .filter(|id| !installed.get(id).map_or(false, |dep| dep.installed.is_some() ) )
+            .filter(|id| !install_queue.iter().any(|a| a.id == *id ) )
+            .collect();
+        if missing.is_empty() {continue;}
+        let before = resolver.explicit_version_date(addon);
+        requests.extend(missing.into_iter().map(|id| DepRequest::new(id, addon, before.clone()) ));
+    }
+    requests.extend(extra);
+
+    if !resolver.search(&requests, install_queue, true)? {
+        let conflict = resolver.explain(&requests, install_queue, true);
+        resolver.steps = 0;
+        if !resolver.search(&requests, install_queue, false)? {
+            bail!("Unresolvable dependencies: {}",resolver.explain(&requests, install_queue, false));
+        }
+        warn!("No dependency versions without incompatibilities: {}",conflict);
+    }
 
-    for dep_id in deps {
-        if installed.get(&dep_id).map_or(false, |dep| dep.installed.is_some() )  {continue}
-        if install_queue.iter().any(|a| a.id == dep_id ) {continue}
+    // dependencies are queued after the addon first requiring them
+    install_queue[start..].reverse();
+    install_queue.rotate_left(start);
 
-        let mut z_channel = channel;
-        let mut z_update_opt = update_opt;
+    Ok(())
+}
+
+const MAX_RESOLVE_STEPS: usize = 10000;
+
+struct Resolver<'a> {
+    installed: &'a LocalAddons,
+    api: &'a mut API,
+    conf: &'a Conf,
+    /// fetched slug, name and files of the dependencies
+    addons: FxHashMap<AddonID,(AddonSlug,String,Vec<AddonFile>)>,
+    steps: usize,
+    /// the first dependency without fitting version the last search ran into
+    conflict: Option<String>,
+}
+
+impl Resolver<'_> {
+    fn search(&mut self, pending: &[DepRequest], queue: &mut Vec<LocalAddon>, honor_incompat: bool) -> Result<bool,anyhow::Error> {
+        let (req,rest) = match pending.split_first() {
+            Some(v) => v,
+            None => return Ok(true),
+        };
+
+        if
+            self.installed.get(&req.id).map_or(false, |dep| dep.installed.is_some() ) ||
+            queue.iter().any(|a| a.id == req.id )
+        {
+            return self.search(rest, queue, honor_incompat);
+        }
+
+        let mut z_channel = req.channel;
+        let z_update_opt = req.update_opt;
         let mut z_manually_installed = false;
-        let mut z_version_blacklist = version_blacklist.clone();
+        let mut z_version_blacklist = req.version_blacklist.clone();
+        let mut z_declined_optionals = vec![];
+        let mut z_version_constraint = None;
 
-        if let Some(local_dep) = installed.get(&dep_id) {
+        if let Some(local_dep) = self.installed.get(&req.id) {
             z_channel = z_channel | local_dep.channel;
             //z_update_opt = local_dep.update_opt; //TODO everything is wrong with the LocalAddon but not installed remove != purge BS, maybe disband it
             z_manually_installed = local_dep.manually_installed;
             z_version_blacklist = local_dep.version_blacklist.clone();
+            z_declined_optionals = local_dep.declined_optionals.clone();
+            z_version_constraint = local_dep.version_constraint.clone();
         }
 
-        let dep_info = match api.addon_info(dep_id) {
+        self.fetch(req.id, &req.parent)?;
+        let (slug,name,files) = &self.addons[&req.id];
+        let (slug,name) = (slug.clone(),name.clone());
+
+        let mut candidates: Vec<AddonFile> = z_channel.candidates(
+            files,
+            self.conf,
+            z_version_blacklist.as_deref(),
+            req.positive_negative_in_filename,
+            z_version_constraint.as_ref(),
+        ).into_iter().cloned().collect();
+
+        if candidates.is_empty() {
+            let reason = if !files.iter().any(|v| self.conf.game_version.matches(v.game_version.iter()) ) {
+                "no version for current game version".to_owned()
+            } else if let Some(constraint) = z_version_constraint.as_ref().filter(|_| files.iter().any(|v| self.conf.filter_addon_file(v, z_version_blacklist.as_deref(), req.positive_negative_in_filename) ) ) {
+                format!("no version in version constraint {}",constraint)
+            } else {
+                "no version for current filter".to_owned()
+            };
+            self.note(format!("{} (required by {}): {}",slug,req.parent,reason));
+            return Ok(false);
+        }
+
+        let newest = candidates[0].clone();
+
+        // versions not newer than an explicitly older parent first
+        if let Some(before) = &req.before {
+            candidates.sort_by_key(|f| f.file_date > *before );
+        }
+
+        let mut incompatible_with: Vec<AddonSlug> = vec![];
+
+        for file in candidates {
+            self.steps += 1;
+            if self.steps > MAX_RESOLVE_STEPS {
+                bail!("Dependency resolution gave up after {} steps",MAX_RESOLVE_STEPS);
+            }
+
+            if honor_incompat {
+                if let Some(other) = incompatible(req.id, &file, queue, self.installed) {
+                    if !incompatible_with.contains(&other) {
+                        incompatible_with.push(other);
+                    }
+                    continue;
+                }
+            }
+
+            let before = file.older_than(&newest).then(|| file.file_date.clone() );
+
+            queue.push(LocalAddon {
+                id: req.id,
+                slug: slug.clone(),
+                name: name.clone(),
+                channel: z_channel,
+                update_opt: z_update_opt,
+                manually_installed: z_manually_installed,
+                version_blacklist: z_version_blacklist.clone(),
+                positive_negative_in_filename: req.positive_negative_in_filename,
+                installed: Some(file),
+                disabled: false,
+                declined_optionals: z_declined_optionals.clone(),
+                version_constraint: z_version_constraint.clone(),
+            });
+
+            // the dependencies of the dependency are resolved with the settings of the original request
+            let mut next = rest.to_vec();
+            next.extend(
+                queue.last().unwrap().installed.as_ref().unwrap().dependencies.iter_required()
+                    .map(|id| DepRequest {
+                        id,
+                        parent: slug.clone(),
+                        before: before.clone(),
+                        ..req.clone()
+                    })
+            );
+
+            if self.search(&next, queue, honor_incompat)? {
+                return Ok(true);
+            }
+
+            queue.pop();
+        }
+
+        if !incompatible_with.is_empty() {
+            let others: Vec<&str> = incompatible_with.iter().map(|s| &*s.0 ).collect();
+            self.note(format!("{} (required by {}): every version is incompatible with {}",slug,req.parent,others.join(", ")));
+        }
+
+        Ok(false)
+    }
+
+    fn fetch(&mut self, id: AddonID, parent: &AddonSlug) -> Result<(),anyhow::Error> {
+        if self.addons.contains_key(&id) {return Ok(());}
+
+        let dep_info = match self.api.addon_info(id) {
             Ok(Some(d)) => d,
-            Ok(None) => bail!("Dependency not available"),
-            Err(e) => bail!("Failed to fetch dependency: {}",e),
+            Ok(None) => bail!("Dependency of {} not available: {}",parent,id),
+            Err(e) => bail!("Failed to fetch dependency of {}: {}",parent,e),
         };
-        let dep_files = match api.files(dep_id) {
+        let dep_files = match self.api.files(id) {
             FilesResult::Ok(v) => v,
-            FilesResult::NotFound => bail!("Dependency not available"),
-            FilesResult::Error(e) => bail!("Failed to fetch dependency: {}",e),
+            FilesResult::NotFound => bail!("Dependency of {} not available: {}",parent,dep_info.slug),
+            FilesResult::Error(e) => bail!("Failed to fetch dependency of {}: {}",parent,e),
         };
 
-        if !dep_files.iter().any(|v| conf.game_version.matches(v.game_version.iter()) ) {
-            bail!("No version for current game version: {}",dep_info.slug);
+        self.addons.insert(id, (dep_info.slug,dep_info.name,dep_files));
+        Ok(())
+    }
+
+    /// file date of the queued addon, if an older than the version picked by its channel was requested
+    fn explicit_version_date(&mut self, addon: &LocalAddon) -> Option<String> {
+        let file = addon.installed.as_ref()?;
+        self.fetch(addon.id, &addon.slug).ok()?;
+        let newest = addon.channel.pick_version(
+            &self.addons[&addon.id].2,
+            self.conf,
+            addon.version_blacklist.as_deref(),
+            addon.positive_negative_in_filename,
+            addon.version_constraint.as_ref(),
+        )?;
+        file.older_than(newest).then(|| file.file_date.clone() )
+    }
+
+    /// reduce the failed requests to a minimal set which still fails, by dropping every request which isn't needed for the failure
+    fn explain(&mut self, requests: &[DepRequest], queue: &mut Vec<LocalAddon>, honor_incompat: bool) -> String {
+        let len = queue.len();
+        let mut core = requests.to_vec();
+        let mut i = 0;
+        while i < core.len() && core.len() > 1 {
+            let mut without = core.clone();
+            without.remove(i);
+            self.steps = 0;
+            // errors like too many steps don't prove the failure, so the request is kept
+            let result = self.search(&without, queue, honor_incompat);
+            // the search only appends to the queue
+            queue.truncate(len);
+            match result {
+                Ok(false) => core = without,
+                _ => i += 1,
+            }
         }
 
-        if !dep_files.iter().any(|v| conf.filter_addon_file(v, version_blacklist.as_deref(), positive_negative_in_filename) ) {
-            bail!("No version for current filter: {}",dep_info.slug);
+        self.steps = 0;
+        self.conflict = None;
+        let _ = self.search(&core, queue, honor_incompat);
+        queue.truncate(len);
+
+        let reqs: Vec<String> = core.iter()
+            .map(|r| {
+                let slug = self.addons.get(&r.id).map_or_else(|| r.id.to_string(), |a| a.0.0.clone() );
+                format!("{} (required by {})",slug,r.parent)
+            })
+            .collect();
+        let reason = self.conflict.take().unwrap_or_default();
+        format!("{} can't be resolved together: {}",reqs.join(", "),reason)
+    }
+
+    fn note(&mut self, conflict: String) {
+        if self.conflict.is_none() {
+            self.conflict = Some(conflict);
         }
+    }
+}
 
-        let dep_file = unwrap_or_bail!(
-            z_channel.pick_version(
-                &dep_files,
-                conf,
-                z_version_blacklist.as_deref(),
-                positive_negative_in_filename,
-            ),
-            "No version found to install"
-        ); //TODO do blacklist
-
-        collect_deps(
-            installed,
-            api,
-            dep_file.dependencies.iter_required(),
-            conf,
-            channel,
-            update_opt,
-            version_blacklist,
-            positive_negative_in_filename,
-            install_queue,
-        )?;
+/// the first queued or installed addon which is incompatible with the file, in either direction
+fn incompatible(id: AddonID, file: &AddonFile, queue: &[LocalAddon], installed: &LocalAddons) -> Option<AddonSlug> {
+    let installed = installed.values()
+        .filter(|a| !queue.iter().any(|q| q.id == a.id ) );
 
-        let new_dep = LocalAddon {
-            id: dep_id,
-            slug: dep_info.slug,
-            name: dep_info.name,
-            channel: z_channel,
-            update_opt: z_update_opt,
-            manually_installed: z_manually_installed,
-            version_blacklist: z_version_blacklist,
-            positive_negative_in_filename,
-            installed: Some(dep_file.clone()),
-        };
+    for other in queue.iter().chain(installed) {
+        if other.id == id {continue;}
+        if let Some(other_file) = &other.installed {
+            if
+                file.dependencies.iter_incompatible().any(|i| i == other.id ) ||
+                other_file.dependencies.iter_incompatible().any(|i| i == id )
+            {
+                return Some(other.slug.clone());
+            }
+        }
+    }
+    None
+}
+
+/// handle the optional dependencies of the queued addons, which aren't installed yet
+/// 
+/// with_optional queues them, else they are listed and remembered as declined in the addon declaring them
+pub fn optional_deps(
+    installed: &LocalAddons,
+    api: &mut API,
+    conf: &Conf,
+    with_optional: bool,
+    install_queue: &mut Vec<LocalAddon>,
+) -> Result<(),anyhow::Error> {
+    let mut missing: Vec<(usize,AddonID)> = vec![];
+
+    for (i,a) in install_queue.iter().enumerate() {
+        for dep_id in a.installed.as_ref().unwrap().dependencies.iter_optional() {
+            if installed.get(&dep_id).map_or(false, |dep| dep.installed.is_some() ) {continue}
+            if install_queue.iter().any(|a| a.id == dep_id ) {continue}
+            if missing.iter().any(|(_,id)| *id == dep_id ) {continue}
+            if !with_optional && a.declined_optionals.contains(&dep_id) {continue}
+            missing.push((i,dep_id));
+        }
+    }
+
+    if missing.is_empty() {return Ok(());}
+
+    if with_optional {
+        let mut requests = vec![];
+        for (i,dep_id) in missing {
+            let parent = &mut install_queue[i];
+            parent.declined_optionals.retain(|id| *id != dep_id );
+            requests.push(DepRequest::new(dep_id, parent, None));
+        }
 
-        install_queue.push(new_dep);
+        resolve_deps(installed, api, conf, requests, install_queue)?;
+    } else {
+        eprintln!("Optional dependencies not installed, install with --with-optional:");
+        for (i,dep_id) in missing {
+            let dep = match api.addon_info(dep_id) {
+                Ok(Some(info)) => info.slug.0,
+                _ => dep_id.to_string(),
+            };
+            eprintln!("\t{} => {}",install_queue[i].slug,dep);
+            install_queue[i].declined_optionals.push(dep_id);
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+fn test_version(id: &str, date: &str, game_version: &str, deps: &[(&str,&str)]) -> String {
+    let deps: Vec<String> = deps.iter()
+        .map(|(id,t)| format!(r#"{{"version_id":null,"project_id":"{id}","file_name":null,"dependency_type":"{t}"}}"#) )
+        .collect();
+    format!(
+        r#"{{"id":"{id}","name":"{id}","version_number":"1.0","date_published":"{date}","version_type":"release","game_versions":["{game_version}"],"loaders":["fabric"],
+        "dependencies":[{}],"files":[{{"hashes":{{"sha1":"aa","sha512":null}},"url":"https://cdn.modrinth.com/{id}.jar","filename":"{id}.jar","primary":true,"size":1}}]}}"#,
+        deps.join(","),
+    )
+}
+
+/// API on a mock Modrinth with the given projects as (id,slug,versions)
+#[cfg(test)]
+fn test_resolve_api(projects: &[(&'static str,&'static str,Vec<String>)]) -> API {
+    let mut routes = vec![];
+    for (id,slug,versions) in projects {
+        routes.push((format!("/project/{id}"),format!(r#"{{"id":"{id}","slug":"{slug}","title":"{slug}","description":""}}"#)));
+        routes.push((format!("/project/{id}/version"),format!("[{}]",versions.join(","))));
+    }
+    let mut api = API::test_api();
+    api.modrinth_domain = crate::api::modrinth::mock_server(routes);
+    api
+}
+
+/// LocalAddon of the given file of the project
+#[cfg(test)]
+fn test_addon(api: &mut API, id: &str, file: &str) -> LocalAddon {
+    let id = AddonID::from_modrinth(id).unwrap();
+    let info = api.addon_info(id).unwrap().unwrap();
+    let file = match api.files(id) {
+        FilesResult::Ok(v) => v.into_iter().find(|f| f.file_name == format!("{file}.jar") ).unwrap(),
+        _ => panic!("no files"),
+    };
+    LocalAddon {
+        id,
+        slug: info.slug,
+        name: info.name,
+        channel: ReleaseTypeMode::new(true,true,true),
+        update_opt: UpdateOpt::All,
+        manually_installed: true,
+        version_blacklist: None,
+        positive_negative_in_filename: false,
+        installed: Some(file),
+        disabled: false,
+        declined_optionals: vec![],
+        version_constraint: None,
+    }
+}
+
+#[cfg(test)]
+fn test_conf() -> Conf {
+    crate::cmd::init::new_repo(crate::addon::GameVersion::new(["1.20.1"]).unwrap()).conf
+}
+
+#[test]
+fn test_resolve_backtrack_incompatible() {
+    // the newest dep version requires a library which is incompatible with an installed addon
+    let mut api = test_resolve_api(&[
+        ("RootAAAA","root",vec![test_version("RootVerA","2023-01-01T00:00:00Z","1.20.1",&[("DepAAAAA","required")])]),
+        ("DepAAAAA","dep",vec![
+            test_version("DepVer1A","2023-01-01T00:00:00Z","1.20.1",&[]),
+            test_version("DepVer2A","2023-02-01T00:00:00Z","1.20.1",&[("LibAAAAA","required")]),
+        ]),
+        ("LibAAAAA","lib",vec![test_version("LibVerAA","2023-01-01T00:00:00Z","1.20.1",&[("BadAAAAA","incompatible")])]),
+        ("BadAAAAA","bad",vec![test_version("BadVerAA","2023-01-01T00:00:00Z","1.20.1",&[])]),
+    ]);
+    let conf = test_conf();
+
+    let mut installed = LocalAddons(Default::default());
+    let bad = test_addon(&mut api, "BadAAAAA", "BadVerAA");
+    installed.insert(bad.id, bad);
+
+    let mut queue = vec![test_addon(&mut api, "RootAAAA", "RootVerA")];
+    resolve_deps(&installed, &mut api, &conf, vec![], &mut queue).unwrap();
+
+    let files: Vec<&str> = queue.iter().map(|a| &*a.installed.as_ref().unwrap().file_name ).collect();
+    assert_eq!(files, ["DepVer1A.jar","RootVerA.jar"]);
+}
+
+#[test]
+fn test_resolve_parent_date() {
+    // an explicitly older parent prefers dep versions from its time
+    let mut api = test_resolve_api(&[
+        ("RootAAAA","root",vec![
+            test_version("RootVer1","2022-01-01T00:00:00Z","1.20.1",&[("DepAAAAA","required")]),
+            test_version("RootVer2","2022-09-01T00:00:00Z","1.20.1",&[("DepAAAAA","required")]),
+        ]),
+        ("DepAAAAA","dep",vec![
+            test_version("DepVer1A","2021-12-01T00:00:00Z","1.20.1",&[]),
+            test_version("DepVer2A","2022-06-01T00:00:00Z","1.20.1",&[]),
+        ]),
+    ]);
+    let conf = test_conf();
+    let installed = LocalAddons(Default::default());
+
+    let mut queue = vec![test_addon(&mut api, "RootAAAA", "RootVer1")];
+    resolve_deps(&installed, &mut api, &conf, vec![], &mut queue).unwrap();
+    assert_eq!(queue[0].installed.as_ref().unwrap().file_name, "DepVer1A.jar");
+
+    let mut queue = vec![test_addon(&mut api, "RootAAAA", "RootVer2")];
+    resolve_deps(&installed, &mut api, &conf, vec![], &mut queue).unwrap();
+    assert_eq!(queue[0].installed.as_ref().unwrap().file_name, "DepVer2A.jar");
+}
+
+#[test]
+fn test_resolve_unresolvable() {
+    // only the dep without version for the game version is in the explanation
+    let mut api = test_resolve_api(&[
+        ("RootAAAA","root",vec![test_version("RootVerA","2023-01-01T00:00:00Z","1.20.1",&[("OkAAAAAA","required"),("DepAAAAA","required")])]),
+        ("OkAAAAAA","okdep",vec![test_version("OkVerAAA","2023-01-01T00:00:00Z","1.20.1",&[])]),
+        ("DepAAAAA","dep",vec![test_version("DepVer1A","2023-01-01T00:00:00Z","1.18.2",&[])]),
+    ]);
+    let conf = test_conf();
+    let installed = LocalAddons(Default::default());
+
+    let mut queue = vec![test_addon(&mut api, "RootAAAA", "RootVerA")];
+    let e = resolve_deps(&installed, &mut api, &conf, vec![], &mut queue).unwrap_err().to_string();
+    assert_eq!(e, "Unresolvable dependencies: dep (required by root) can't be resolved together: dep (required by root): no version for current game version");
+    assert_eq!(queue.len(), 1);
+}
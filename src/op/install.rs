@@ -6,9 +6,13 @@ use crate::addon::local::{LocalAddon, UpdateOpt};
 use crate::addon::rtm::ReleaseTypeMode;
 use crate::api::API;
 use crate::conf::Repo;
+use crate::addon::download::FilePaths;
 use crate::util::fs::Finalize;
-use super::deps::collect_deps;
+use crate::util::pool::par_map;
+use super::deps::{optional_deps, resolve_deps};
 use super::incompat::*;
+use super::autoremove::orphaned_deps;
+use super::remove::autoremove_addons;
 use crate::{Op, error, warn, log_error};
 
 pub fn install_mod(
@@ -24,13 +28,15 @@ pub fn install_mod(
     manually_installed: bool,
     version_blacklist: Option<String>,
     positive_negative_in_filename: bool,
+    with_optional: bool,
+    autoremove: bool,
     // oof
     o: &Op,
     api: &mut API,
     repo: &mut Repo,
 ) -> Result<bool,anyhow::Error> {
     // if current mod installed, add to delete_sched
-    // iterate required deps of to install file recursively and if not already installed, collect to install_sched, choose the latest version matching channel
+    // resolve required deps of to install file recursively and if not already installed, collect to install_sched, choose the latest version matching channel which isn't incompatible
     // - only deps that aren't installed are now installed
     // - TODO what if dep's LocalAddon still exists?
     // do check_incompatibility_2 with install_sched
@@ -39,28 +45,70 @@ pub fn install_mod(
     // - on deps will softly derive from "our" LocalParams and existing LocalAddon (if removed but not purged) TODO how
     // - on "this" addon, "our" LocalParams will replace the ones of existing LocalAddon
     // run install finalizers and delete_sched
+    // deps only required by the replaced version are listed, or removed with autoremove
 
     let mut install_queue = vec![];
-    let mut finalizer_queue = vec![];
-    let mut installed_queue = vec![];
 
-    collect_deps(
-        &repo.addons,
-        api,
-        install.dependencies.iter_required(),
-        &repo.conf,
+    install_queue.push(LocalAddon {
+        id: addon_id,
+        slug: i_slug,
+        name: i_name,
         channel,
         update_opt,
-        &version_blacklist,
+        manually_installed,
+        version_blacklist,
         positive_negative_in_filename,
-        &mut install_queue,
-    )?;
+        installed: Some(install),
+        disabled: false,
+        declined_optionals: repo.addons.get(&addon_id).map_or(vec![], |a| a.declined_optionals.clone() ),
+        version_constraint: repo.addons.get(&addon_id).and_then(|a| a.version_constraint.clone() ),
+    });
+
+    resolve_deps(&repo.addons, api, &repo.conf, vec![], &mut install_queue)?;
+
+    optional_deps(&repo.addons, api, &repo.conf, with_optional, &mut install_queue)?;
+
+    // dependencies of the replaced version which aren't required anymore
+    let orphaned = orphaned_deps(&install_queue, &repo.addons);
+
+    if !autoremove && !orphaned.is_empty() {
+        eprintln!("Dependencies not required anymore, remove with --autoremove:");
+        for id in &orphaned {
+            eprintln!("\t{}",repo.addons.get(id).unwrap().slug);
+        }
+    }
+
+    let autoremove = if autoremove {&orphaned[..]} else {&[]};
+
+    install_addons(install_queue, autoremove, force_incompat, o, api, repo)
+}
 
+/// Install the resolved addons (with installed set), either all addons get installed or none
+///
+/// the files of the autoremove addons are removed together with the previous versions
+pub fn install_addons(
+    install_queue: Vec<LocalAddon>,
+    autoremove: &[AddonID],
+    force_incompat: bool,
+    o: &Op,
+    api: &mut API,
+    repo: &mut Repo,
+) -> Result<bool,anyhow::Error> {
     let incompat = check_incompatibility_3(
         &install_queue,
         &repo.addons,
     );
 
+    {
+        let mut plan = o.plan();
+        for i in &install_queue {
+            plan.install(i, &repo.addons);
+        }
+        for i in &incompat {
+            plan.incompatible(i);
+        }
+    }
+
     if !incompat.is_empty() {
         if !force_incompat {
             error!("Incompatible addons:{}",o.suffix());
@@ -77,103 +125,110 @@ pub fn install_mod(
         }
     }
 
-    let mut modified = false;
+    // fetch all files in parallel, finalizers are created in order below
+    if !o.noop {
+        let files: Vec<(AddonID,&AddonFile)> = install_queue.iter()
+            .map(|i| (i.id,i.installed.as_ref().unwrap()) )
+            .collect();
+        for (i,result) in install_queue.iter().zip(prefetch_addons(&files, o, api, repo)) {
+            result.map_err(|e| anyhow!("Failed to install addon {}: {}",i.slug,e))?;
+        }
+    }
+
+    let mut finalizer_queue = vec![];
+    let mut installed_queue = vec![];
+    let mut replaced_queue = vec![];
+
+    for mut i in install_queue {
+        let to_install = i.installed.as_ref().unwrap();
 
-    for i in install_queue {
         eprintln!(
             "Install: {} ({}){}",
             i.slug,
-            i.installed.as_ref().unwrap().file_name,
+            to_install.file_name,
             o.suffix()
         );
 
-        if i.installed.as_ref().unwrap().has_install_script {
+        if to_install.has_install_script {
             warn!(
                 "Installing {}: Install Scripts are currently unsupported", 
                 i.slug
             );
         }
 
-        if !o.noop {
-            let dep_to_install = i.installed.as_ref().unwrap();
+        let (prev_paths,install_paths) = install_paths(i.id, to_install, i.disabled, o, repo);
 
-            let dep_install_paths = dep_to_install.file_paths_new(
-                i.id,
-                false,
-                &repo.conf,
-            );
+        i.disabled = install_paths.disabled;
 
-            let finalizer = dep_to_install.download(&dep_install_paths, &repo.conf, api, false)
-                .map_err(|e| anyhow!("Failed to install dependency addon: {}",e))?;
+        if !o.noop {
+            let finalizer = to_install.download(&install_paths, &repo.conf, api, false)
+                .map_err(|e| anyhow!("Failed to install addon {}: {}",i.slug,e))?;
 
             finalizer_queue.push(finalizer);
-            installed_queue.push((i.id,i));
-        }
-    }
-
-    eprintln!(
-        "Install: {} ({}){}",
-        i_slug,
-        install.file_name,
-        o.suffix()
-    );
-
-    let prev_paths = repo.addons.get(&addon_id)
-        .and_then(|a| a.installed.as_ref() )
-        .map(|f| f.file_paths_current(addon_id, !o.noop, &repo.conf) );
-
-    let mut installed_paths = None;
-
-    if !o.noop {
-        let install_paths = install.file_paths_new(
-            addon_id,
-            prev_paths.as_ref().map_or(false, |prev| prev.disabled),
-            &repo.conf,
-        );
 
-        let finalizer = install.download(&install_paths, &repo.conf, api, false)
-            .map_err(|e| anyhow!("Failed to install addon: {}",e))?;
-
-        finalizer_queue.push(finalizer);
-    
-        installed_queue.push((addon_id,LocalAddon {
-            id: addon_id,
-            slug: i_slug,
-            name: i_name,
-            channel,
-            update_opt,
-            manually_installed,
-            version_blacklist,
-            positive_negative_in_filename,
-            installed: Some(install),
-        }));
-
-        installed_paths = Some(install_paths);
-    }
+            installed_queue.push(i);
+        }
 
-    if let Some(installed_paths) = installed_paths {
+        // also with noop, to show the removal
         if let Some(prev_paths) = prev_paths {
-            if installed_paths.path != prev_paths.path {
-                eprintln!(
-                    "Remove previous version: {}{}",
-                    prev_paths.path.to_string_lossy(),
-                    o.suffix()
-                );
-            }
-            if !o.noop {
-                log_error!(prev_paths.remove_if_not_new(&installed_paths), |e| "Failed to remove addon: {}",e);
-                //addon.installed = None; //at that point, the entire install fn succeeded
-                modified = true;
-            }
+            replaced_queue.push((prev_paths,install_paths));
         }
     }
 
     Finalize::finalize_slice(&mut finalizer_queue)?;
 
-    for (id,i) in installed_queue {
-        repo.addons.insert(id,i);
+    let mut modified = false;
+
+    for (prev_paths,installed_paths) in replaced_queue {
+        if installed_paths.path != prev_paths.path {
+            eprintln!(
+                "Remove previous version: {}{}",
+                prev_paths.path.to_string_lossy(),
+                o.suffix()
+            );
+        }
+        if !o.noop {
+            log_error!(prev_paths.remove_if_not_new(&installed_paths), |e| "Failed to remove addon: {}",e);
+            modified = true;
+        }
+    }
+
+    for i in installed_queue {
+        repo.addons.insert(i.id,i);
         modified = true;
     }
 
+    modified |= autoremove_addons(autoremove, o, repo);
+
     Ok(modified)
 }
+
+/// paths of the previous and the new addon file, the addon is kept disabled if it was disabled before
+fn install_paths(id: AddonID, file: &AddonFile, disabled: bool, o: &Op, repo: &Repo) -> (Option<FilePaths>,FilePaths) {
+    let prev = repo.addons.get(&id);
+
+    let prev_paths = prev
+        .and_then(|a| a.installed.as_ref() )
+        .map(|f| f.file_paths_current(id, !o.noop, &repo.conf) );
+
+    let disabled = disabled ||
+        prev_paths.as_ref().map_or(false, |prev| prev.disabled) ||
+        prev.map_or(false, |a| a.disabled );
+
+    let paths = file.file_paths_new(id, disabled, &repo.conf);
+
+    (prev_paths,paths)
+}
+
+/// download the files of the addons in parallel, so the install only needs to move them into place
+pub fn prefetch_addons(files: &[(AddonID,&AddonFile)], o: &Op, api: &API, repo: &Repo) -> Vec<anyhow::Result<()>> {
+    let jobs: Vec<(&AddonFile,FilePaths)> = files.iter()
+        .map(|&(id,file)| (file,install_paths(id, file, false, o, repo).1) )
+        .collect();
+
+    let http = api.http();
+
+    par_map(&jobs, repo.conf.download_concurrency, |(file,paths)| {
+        file.prefetch(paths, &repo.conf, &http, false)
+    })
+}
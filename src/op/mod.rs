@@ -5,3 +5,4 @@ pub mod channel;
 pub mod remove;
 pub mod install;
 pub mod update;
+pub mod plan;
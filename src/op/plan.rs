@@ -0,0 +1,151 @@
+use std::cell::RefMut;
+
+use serde_derive::*;
+
+use crate::Op;
+use crate::addon::files::AddonFile;
+use crate::addon::local::{LocalAddon, LocalAddons};
+use super::incompat::Incompat;
+
+/// What a mutating command does to the repo, collected before it is done
+///
+/// With --format json it is printed at the end, together with --noop it is the dry-run plan
+#[derive(Serialize,Default)]
+pub struct Plan {
+    pub installs: Vec<PlanAddon>,
+    pub upgrades: Vec<PlanAddon>,
+    pub downgrades: Vec<PlanAddon>,
+    /// dependencies installed along with the addons
+    pub dependency_additions: Vec<PlanAddon>,
+    pub removals: Vec<PlanAddon>,
+    /// addon files not in the repo, removed by sync
+    pub file_removals: Vec<String>,
+    pub incompatibilities: Vec<PlanIncompat>,
+}
+
+#[derive(Serialize)]
+pub struct PlanAddon {
+    pub id: String,
+    pub slug: String,
+    /// currently installed file
+    pub from: Option<String>,
+    /// file to be installed
+    pub to: Option<String>,
+    /// purged instead of only removed
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub purge: bool,
+}
+
+#[derive(Serialize)]
+pub struct PlanIncompat {
+    pub from: String,
+    pub to: String,
+}
+
+impl Plan {
+    /// sort the queued addon by its installed version
+    pub fn install(&mut self, addon: &LocalAddon, addons: &LocalAddons) {
+        let to = addon.installed.as_ref().unwrap();
+        let from = addons.get(&addon.id).and_then(|a| a.installed.as_ref() );
+
+        let entry = PlanAddon::new(addon, from, Some(to), false);
+
+        match from {
+            Some(from) if from.id == to.id => {},
+            Some(from) if to.older_than(from) => self.downgrades.push(entry),
+            Some(_) => self.upgrades.push(entry),
+            None if !addon.manually_installed => self.dependency_additions.push(entry),
+            None => self.installs.push(entry),
+        }
+    }
+
+    /// the installed file of the addon is missing or invalid and downloaded again
+    pub fn download(&mut self, addon: &LocalAddon) {
+        self.installs.push(PlanAddon::new(addon, None, addon.installed.as_ref(), false));
+    }
+
+    pub fn remove(&mut self, addon: &LocalAddon, purge: bool) {
+        self.removals.push(PlanAddon::new(addon, addon.installed.as_ref(), None, purge));
+    }
+
+    pub fn remove_file(&mut self, file_name: &str) {
+        self.file_removals.push(file_name.to_owned());
+    }
+
+    pub fn incompatible(&mut self, incompat: &Incompat) {
+        self.incompatibilities.push(PlanIncompat {
+            from: incompat.from.slug.0.clone(),
+            to: incompat.to.slug.0.clone(),
+        });
+    }
+
+    pub fn print_json(&self) {
+        println!("{}",serde_jsonrc::to_string_pretty(self).unwrap());
+    }
+}
+
+impl PlanAddon {
+    fn new(addon: &LocalAddon, from: Option<&AddonFile>, to: Option<&AddonFile>, purge: bool) -> Self {
+        Self {
+            id: addon.id.to_string(),
+            slug: addon.slug.0.clone(),
+            from: from.map(|f| f.file_name.clone() ),
+            to: to.map(|f| f.file_name.clone() ),
+            purge,
+        }
+    }
+}
+
+impl Op {
+    /// the plan of the running command
+    pub fn plan(&self) -> RefMut<'_,Plan> {
+        self.plan.borrow_mut()
+    }
+}
+
+#[test]
+fn test_plan_install() {
+    use crate::addon::{AddonID, AddonSlug};
+    use crate::addon::local::UpdateOpt;
+    use crate::addon::rtm::ReleaseTypeMode;
+
+    let file = |id: u64| -> AddonFile {
+        serde_jsonrc::from_str(&format!(r#"{{
+            "id":{id},"displayName":"f{id}","fileName":"f{id}.jar","fileDate":"2023-01-01T00:00:00Z","fileLength":1,"releaseType":1,
+            "isAlternate":false,"alternateFileId":0,"dependencies":[],"isAvailable":true,"packageFingerprint":0,"gameVersion":[],"hasInstallScript":false
+        }}"#)).unwrap()
+    };
+    let addon = |id: u64, installed: Option<AddonFile>, manually_installed: bool| LocalAddon {
+        id: AddonID(id),
+        slug: AddonSlug(format!("a{id}")),
+        name: format!("a{id}"),
+        channel: ReleaseTypeMode::new(true,true,true),
+        update_opt: UpdateOpt::All,
+        manually_installed,
+        version_blacklist: None,
+        positive_negative_in_filename: false,
+        installed,
+        disabled: false,
+        declined_optionals: vec![],
+        version_constraint: None,
+    };
+
+    let mut addons = LocalAddons(Default::default());
+    addons.insert(AddonID(1), addon(1, Some(file(10)), true));
+    addons.insert(AddonID(2), addon(2, Some(file(20)), true));
+    addons.insert(AddonID(3), addon(3, Some(file(30)), true));
+
+    let mut plan = Plan::default();
+    plan.install(&addon(1, Some(file(11)), true), &addons);
+    plan.install(&addon(2, Some(file(19)), true), &addons);
+    plan.install(&addon(3, Some(file(30)), true), &addons);
+    plan.install(&addon(4, Some(file(40)), false), &addons);
+    plan.install(&addon(5, Some(file(50)), true), &addons);
+
+    let slugs = |v: &[PlanAddon]| v.iter().map(|a| a.slug.clone() ).collect::<Vec<_>>();
+    assert_eq!(slugs(&plan.upgrades), ["a1"]);
+    assert_eq!(slugs(&plan.downgrades), ["a2"]);
+    assert_eq!(slugs(&plan.dependency_additions), ["a4"]);
+    assert_eq!(slugs(&plan.installs), ["a5"]);
+    assert_eq!(plan.upgrades[0].from.as_deref(), Some("f10.jar"));
+}
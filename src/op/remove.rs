@@ -1,5 +1,7 @@
+use crate::{Op, error};
 use crate::addon::AddonID;
 use crate::addon::local::{LocalAddon, LocalAddons};
+use crate::conf::Repo;
 
 pub fn has_dependents(id: AddonID, addons: &LocalAddons) -> Vec<&LocalAddon> {
     let mut dest = Vec::new();
@@ -16,3 +18,28 @@ pub fn has_dependents(id: AddonID, addons: &LocalAddons) -> Vec<&LocalAddon> {
     
     dest
 }
+
+/// remove the files of the auto-installed addons, the LocalAddon is kept like on remove
+///
+/// failures are only logged, as this runs after the installs they belong to are already done
+pub fn autoremove_addons(ids: &[AddonID], o: &Op, repo: &mut Repo) -> bool {
+    let mut modified = false;
+
+    for &id in ids {
+        let addon = repo.addons.get_mut(&id).unwrap();
+        o.plan().remove(addon, false);
+        eprintln!("Autoremove: {}{}",addon.slug,o.suffix());
+        if !o.noop {
+            let paths = addon.installed.as_ref().unwrap().file_paths_current(id, !o.noop, &repo.conf);
+            match paths.remove() {
+                Ok(_) => {
+                    addon.installed = None;
+                    modified = true;
+                },
+                Err(e) => error!("Failed to remove addon {}: {}",addon.slug,e),
+            }
+        }
+    }
+
+    modified
+}
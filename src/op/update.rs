@@ -1,22 +1,23 @@
-use crate::addon::{FileID, GameVersion};
 use crate::addon::files::AddonFile;
 use crate::addon::rtm::ReleaseTypeMode;
+use crate::addon::version_constraint::VersionConstraint;
 use crate::conf::Conf;
 use crate::warn;
 
 pub fn find_version_update<'a>(
     versions: &'a [AddonFile],
-    installed: Option<FileID>,
+    installed: Option<&AddonFile>,
     conf: &Conf,
     blacklist: Option<&str>,
     positive_negative_in_filename: bool,
     release_type: ReleaseTypeMode,
     allow_downgrade: bool,
+    constraint: Option<&VersionConstraint>,
 ) -> Option<&'a AddonFile> {
     let mut current_idx = 0; // includes current version
     if let Some(installed) = installed {
         for v in versions {
-            if v.id.0 < installed.0 {
+            if v.older_than(installed) {
                 current_idx += 1;
             } else {
                 break
@@ -32,8 +33,8 @@ pub fn find_version_update<'a>(
         current_idx..versions.len()
     };
 
-    release_type.pick_version(&versions[visible_range], conf, blacklist, positive_negative_in_filename)
-        .filter(|f| installed.is_none() || f.id != installed.unwrap() )
+    release_type.pick_version(&versions[visible_range], conf, blacklist, positive_negative_in_filename, constraint)
+        .filter(|f| installed.map_or(true, |i| f.id != i.id ) )
 }
 
 pub fn fix_discrepancy(
@@ -69,7 +70,7 @@ pub fn fix_discrepancy(
         // find the next slot after the last with smaller id
         let slot = versions.iter()
             .enumerate()
-            .find(|(_,v)| v.id.0 > installed.id.0 )
+            .find(|(_,v)| installed.older_than(v) )
             .map(|(i,_)| i )
             .unwrap_or(versions.len());
 
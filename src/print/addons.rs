@@ -2,17 +2,18 @@ use std::usize;
 
 use crate::addon::local::{LocalAddon, LocalAddons};
 use crate::addon::release_type::ReleaseType;
-use crate::addon::{AddonSlug, GameVersion};
+use crate::addon::AddonSlug;
 use crate::api::AddonInfo;
+use crate::conf::Conf;
 use super::*;
 
 pub fn print_addons_search<'a>(
     addons: impl Iterator<Item=&'a AddonInfo>,
-    game_version: &GameVersion,
+    conf: &Conf,
     installed: &LocalAddons,
 ){
     for a in addons {
-        if let Some(release_type) = a.release_type(game_version) {
+        if let Some(release_type) = a.release_type(conf) {
             let installed = installed.get(&a.id).and_then(|a| a.installed.as_ref() );
             print_addon(
                 &a.slug,
@@ -10,6 +10,7 @@ use crate::addon::release_type::ReleaseType;
 pub mod versions;
 pub mod addons;
 pub mod error;
+pub mod records;
 
 pub fn color_of_release_type(rt: &ReleaseType) -> Koller {
     match rt {
@@ -0,0 +1,193 @@
+use serde_derive::*;
+
+use crate::{OutputFormat, hard_error};
+use crate::addon::files::AddonFile;
+use crate::addon::local::{LocalAddon, LocalAddons};
+use crate::addon::release_type::ReleaseType;
+use crate::api::AddonInfo;
+use crate::conf::Conf;
+
+/// record of an installed or found addon, for --format json|tsv
+#[derive(Serialize)]
+pub struct AddonRecord {
+    pub slug: String,
+    pub id: String,
+    pub name: String,
+    pub file_id: Option<String>,
+    pub file_name: Option<String>,
+    pub release_type: Option<String>,
+    pub channel: Option<String>,
+    /// best release type available for the game version, from search
+    pub available_release_type: Option<String>,
+    pub update_file_id: Option<String>,
+    pub update_file_name: Option<String>,
+    pub update_release_type: Option<String>,
+    /// ids of the required dependencies of the installed file
+    pub dependencies: Vec<String>,
+}
+
+/// record of an addon version, for --format json|tsv
+#[derive(Serialize)]
+pub struct VersionRecord {
+    pub file_id: String,
+    pub file_name: String,
+    pub display_name: String,
+    pub release_type: String,
+    pub file_date: String,
+    pub installed: bool,
+    pub dependencies: Vec<String>,
+}
+
+impl AddonRecord {
+    pub fn local(addon: &LocalAddon) -> Self {
+        let file = addon.installed.as_ref();
+        Self {
+            slug: addon.slug.0.clone(),
+            id: addon.id.to_string(),
+            name: addon.name.clone(),
+            file_id: file.map(|f| f.id.to_string() ),
+            file_name: file.map(|f| f.file_name.clone() ),
+            release_type: file.map(|f| release_type_str(f.release_type) ),
+            channel: Some(addon.channel.to_string()),
+            available_release_type: None,
+            update_file_id: None,
+            update_file_name: None,
+            update_release_type: None,
+            dependencies: file.map_or(vec![], required_deps),
+        }
+    }
+
+    pub fn search(info: &AddonInfo, conf: &Conf, installed: &LocalAddons) -> Self {
+        let local = installed.get(&info.id).filter(|a| a.installed.is_some() );
+        let mut record = match local {
+            Some(local) => Self::local(local),
+            None => Self {
+                slug: info.slug.0.clone(),
+                id: info.id.to_string(),
+                name: info.name.clone(),
+                file_id: None,
+                file_name: None,
+                release_type: None,
+                channel: None,
+                available_release_type: None,
+                update_file_id: None,
+                update_file_name: None,
+                update_release_type: None,
+                dependencies: vec![],
+            },
+        };
+        record.available_release_type = info.release_type(conf).map(release_type_str);
+        record
+    }
+
+    pub fn with_update(mut self, update: Option<&AddonFile>) -> Self {
+        if let Some(update) = update {
+            self.update_file_id = Some(update.id.to_string());
+            self.update_file_name = Some(update.file_name.clone());
+            self.update_release_type = Some(release_type_str(update.release_type));
+        }
+        self
+    }
+}
+
+impl VersionRecord {
+    pub fn new(file: &AddonFile, installed: Option<&AddonFile>) -> Self {
+        Self {
+            file_id: file.id.to_string(),
+            file_name: file.file_name.clone(),
+            display_name: file.display_name.clone(),
+            release_type: release_type_str(file.release_type),
+            file_date: file.file_date.clone(),
+            installed: installed.map_or(false, |i| i.id == file.id ),
+            dependencies: required_deps(file),
+        }
+    }
+}
+
+pub trait Record: serde::Serialize {
+    const COLUMNS: &'static [&'static str];
+    fn row(&self) -> Vec<String>;
+}
+
+impl Record for AddonRecord {
+    const COLUMNS: &'static [&'static str] = &[
+        "slug","id","name","file_id","file_name","release_type","channel","available_release_type",
+        "update_file_id","update_file_name","update_release_type","dependencies",
+    ];
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.slug.clone(),
+            self.id.clone(),
+            self.name.clone(),
+            opt(&self.file_id),
+            opt(&self.file_name),
+            opt(&self.release_type),
+            opt(&self.channel),
+            opt(&self.available_release_type),
+            opt(&self.update_file_id),
+            opt(&self.update_file_name),
+            opt(&self.update_release_type),
+            self.dependencies.join(","),
+        ]
+    }
+}
+
+impl Record for VersionRecord {
+    const COLUMNS: &'static [&'static str] = &[
+        "file_id","file_name","display_name","release_type","file_date","installed","dependencies",
+    ];
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.file_id.clone(),
+            self.file_name.clone(),
+            self.display_name.clone(),
+            self.release_type.clone(),
+            self.file_date.clone(),
+            self.installed.to_string(),
+            self.dependencies.join(","),
+        ]
+    }
+}
+
+/// print the records to stdout, as JSON array or TSV with header line
+pub fn print_records<R: Record>(records: &[R], format: OutputFormat) {
+    match format {
+        OutputFormat::Text => {},
+        OutputFormat::Dot => hard_error!("Format dot is only supported by tree and why"),
+        OutputFormat::Json => println!("{}",serde_jsonrc::to_string_pretty(records).unwrap()),
+        OutputFormat::Tsv => {
+            println!("{}",R::COLUMNS.join("\t"));
+            for r in records {
+                let row: Vec<String> = r.row().iter().map(|v| v.replace(['\t','\n','\r']," ") ).collect();
+                println!("{}",row.join("\t"));
+            }
+        },
+    }
+}
+
+fn release_type_str(rt: ReleaseType) -> String {
+    rt.to_string().to_lowercase()
+}
+
+fn required_deps(file: &AddonFile) -> Vec<String> {
+    file.dependencies.iter_required().map(|id| id.to_string() ).collect()
+}
+
+fn opt(v: &Option<String>) -> String {
+    v.clone().unwrap_or_default()
+}
+
+#[test]
+fn test_tsv_row_columns() {
+    let record = VersionRecord {
+        file_id: "cf:1".to_owned(),
+        file_name: "a.jar".to_owned(),
+        display_name: "a".to_owned(),
+        release_type: "release".to_owned(),
+        file_date: "2021-02-13T20:36:05Z".to_owned(),
+        installed: true,
+        dependencies: vec!["cf:2".to_owned(),"cf:3".to_owned()],
+    };
+    assert_eq!(record.row().len(), VersionRecord::COLUMNS.len());
+    assert_eq!(record.row()[6], "cf:2,cf:3");
+}
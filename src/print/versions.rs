@@ -19,7 +19,7 @@ pub fn print_versions(
     let mut current_idx = 0; // includes current version
     if let Some(current) = current {
         for v in versions {
-            if v.file().id.0 < current.id.0 {
+            if v.file().older_than(current) {
                 current_idx += 1;
             } else {
                 break
@@ -1,7 +1,10 @@
 use std::ffi::{OsStr, OsString};
 use std::io::ErrorKind;
-use std::path::{Path, PathBuf};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Component, Path, PathBuf};
 use anyhow::bail;
+use filetime::{FileTime, set_file_mtime};
+use serde_derive::*;
 
 use crate::{log_error, error};
 
@@ -208,8 +211,121 @@ pub fn create_guarded_symlink_lazy(src: PathBuf, dest: PathBuf) -> anyhow::Resul
     }
 }
 
+/// How files of the shared cache are placed into the addon dir
+#[derive(Deserialize,Serialize,Clone,Copy,PartialEq,Eq,Debug,Default)]
+#[serde(rename_all="lowercase")]
+pub enum CacheLinkMode {
+    #[default]
+    Symlink,
+    Hardlink,
+    /// copy-on-write clone, falls back to copy if the filesystem doesn't support it
+    Reflink,
+    Copy,
+}
+
+impl CacheLinkMode {
+    /// whether dest is placed from the cache file src in this mode, the content is not checked
+    pub fn is_linked(self, src: &Path, dest: &Path) -> bool {
+        let dest_meta = match dest.symlink_metadata() {
+            Ok(v) => v,
+            Err(_) => return false,
+        };
+        let is_hardlink = || src.metadata()
+            .map_or(false, |src_meta| src_meta.dev() == dest_meta.dev() && src_meta.ino() == dest_meta.ino() );
+        match self {
+            Self::Symlink => dest_meta.is_symlink(),
+            Self::Hardlink => is_hardlink(),
+            // modifying a hardlinked copy would also modify the cache
+            Self::Reflink | Self::Copy => dest_meta.is_file() && !is_hardlink(),
+        }
+    }
+}
+
+/// like create_guarded_symlink_lazy, but for every CacheLinkMode
+/// 
+/// Except symlinks, the file is placed to the .part of dest first and renamed on finalize
+pub fn create_guarded_cache_link(src: PathBuf, dest: PathBuf, mode: CacheLinkMode) -> anyhow::Result<Finalize> {
+    if mode == CacheLinkMode::Symlink {
+        return create_guarded_symlink_lazy(src, dest);
+    }
+    if src == dest {
+        return Ok(Finalize::noop());
+    }
+    if dest.symlink_metadata().map_or(false, |meta| meta.is_dir() ) {
+        bail!("Directory");
+    }
+
+    let part = part_file_path(&dest);
+    remove_if(&part)?;
+
+    match mode {
+        CacheLinkMode::Hardlink => std::fs::hard_link(&src, &part)?,
+        CacheLinkMode::Reflink => reflink_or_copy(&src, &part)?,
+        CacheLinkMode::Copy => {std::fs::copy(&src, &part)?;},
+        CacheLinkMode::Symlink => unreachable!(),
+    }
+
+    if mode != CacheLinkMode::Hardlink {
+        // keep the addon mtime of the cache file
+        log_error!(set_file_mtime(&part, FileTime::from_last_modification_time(&src.metadata()?)));
+    }
+
+    Ok(Finalize::for_part_path(dest, part, false))
+}
+
+fn reflink_or_copy(src: &Path, dest: &Path) -> anyhow::Result<()> {
+    #[cfg(target_os = "linux")] {
+        use std::os::unix::io::AsRawFd;
+
+        const FICLONE: libc::c_ulong = 0x40049409;
+
+        let src_file = std::fs::File::open(src)?;
+        let dest_file = std::fs::File::create(dest)?;
+        if unsafe { libc::ioctl(dest_file.as_raw_fd(), FICLONE as _, src_file.as_raw_fd()) } == 0 {
+            return Ok(());
+        }
+        crate::dark_log!("Reflink not supported, copy: {}",std::io::Error::last_os_error());
+    }
+    std::fs::copy(src, dest)?;
+    Ok(())
+}
+
+/// path of the existing file relative to the existing dir, None if there is no relative path between them
+pub fn relative_path(path: &Path, base: &Path) -> std::io::Result<Option<PathBuf>> {
+    let path = std::fs::canonicalize(path)?;
+    let base = std::fs::canonicalize(base)?;
+
+    let mut path_c = path.components().peekable();
+    let mut base_c = base.components().peekable();
+    // different roots, e.g. drives
+    if path_c.peek() != base_c.peek() {
+        return Ok(None);
+    }
+    while path_c.peek().is_some() && path_c.peek() == base_c.peek() {
+        path_c.next();
+        base_c.next();
+    }
+
+    let mut dest: PathBuf = base_c.map(|_| Component::ParentDir ).collect();
+    dest.extend(path_c);
+    Ok(Some(dest))
+}
+
 pub fn attached_to_path(path: impl Into<PathBuf>, add: impl AsRef<OsStr>) -> PathBuf {
     let mut path = path.into().into_os_string();
     path.push(add);
     path.into()
 }
+
+#[test]
+fn test_relative_path() {
+    let dir = std::env::temp_dir().join(format!("cursinator_test_relative_path_{}",std::process::id()));
+    std::fs::create_dir_all(dir.join("repo/mods")).unwrap();
+    std::fs::create_dir_all(dir.join("vendor")).unwrap();
+    std::fs::write(dir.join("vendor/a.jar"), b"a").unwrap();
+
+    assert_eq!(relative_path(&dir.join("vendor/a.jar"), &dir.join("repo/mods")).unwrap(), Some(PathBuf::from("../../vendor/a.jar")));
+    assert_eq!(relative_path(&dir.join("vendor/a.jar"), &dir.join("vendor")).unwrap(), Some(PathBuf::from("a.jar")));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,35 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Run f for all items on up to concurrency threads, results are in order of items
+pub fn par_map<T: Sync, R: Send>(items: &[T], concurrency: usize, f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let threads = concurrency.max(1).min(items.len());
+    if threads <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None ).collect());
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= items.len() {break}
+                    let result = f(&items[i]);
+                    results.lock().unwrap()[i] = Some(result);
+                }
+            });
+        }
+    });
+
+    results.into_inner().unwrap().into_iter().map(Option::unwrap).collect()
+}
+
+#[test]
+fn test_par_map() {
+    let items: Vec<u32> = (0..100).collect();
+    assert_eq!(par_map(&items, 8, |i| i * 2 ), items.iter().map(|i| i * 2 ).collect::<Vec<_>>());
+    assert!(par_map(&[] as &[u32], 8, |i| *i ).is_empty());
+}